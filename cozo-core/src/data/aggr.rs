@@ -12,7 +12,7 @@ use std::fmt::{Debug, Formatter};
 use miette::{bail, ensure, miette, Result};
 use rand::prelude::*;
 
-use crate::data::value::DataValue;
+use crate::data::value::{DataValue, Num};
 
 pub(crate) struct Aggregation {
     pub(crate) name: &'static str,
@@ -21,6 +21,48 @@ pub(crate) struct Aggregation {
     pub(crate) normal_op: Option<Box<dyn NormalAggrObj>>,
 }
 
+/// Caps on the number of values buffered by aggregations such as `collect`,
+/// set by the `:max_group_values` and `:max_aggr_values` query options.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct AggrLimits {
+    /// Values buffered by a single aggregation for a single group
+    pub(crate) per_group: Option<usize>,
+    /// Values buffered by all aggregations of a rule together
+    pub(crate) total: Option<usize>,
+}
+
+impl AggrLimits {
+    /// Account for an aggregation going from `before` to `after` buffered values,
+    /// with `total` the running count for the rule being evaluated.
+    pub(crate) fn track(
+        &self,
+        name: &str,
+        before: usize,
+        after: usize,
+        total: &mut usize,
+    ) -> Result<()> {
+        *total = *total - before + after;
+        if let Some(cap) = self.per_group {
+            ensure!(
+                after <= cap,
+                "aggregation '{}' buffered more than {} values for a single group, \
+                 as limited by ':max_group_values'",
+                name,
+                cap
+            );
+        }
+        if let Some(cap) = self.total {
+            ensure!(
+                *total <= cap,
+                "aggregations buffered more than {} values in total, \
+                 as limited by ':max_aggr_values'",
+                cap
+            );
+        }
+        Ok(())
+    }
+}
+
 impl Clone for Aggregation {
     fn clone(&self) -> Self {
         Self {
@@ -35,6 +77,10 @@ impl Clone for Aggregation {
 pub(crate) trait NormalAggrObj: Send + Sync {
     fn set(&mut self, value: &DataValue) -> Result<()>;
     fn get(&self) -> Result<DataValue>;
+    /// Number of values held in memory, for aggregations that buffer their inputs.
+    fn buffered(&self) -> usize {
+        0
+    }
 }
 
 pub(crate) trait MeetAggrObj: Send + Sync {
@@ -166,6 +212,10 @@ impl NormalAggrObj for AggrUnique {
     fn get(&self) -> Result<DataValue> {
         Ok(DataValue::List(self.accum.iter().cloned().collect()))
     }
+
+    fn buffered(&self) -> usize {
+        self.accum.len()
+    }
 }
 
 define_aggr!(AGGR_GROUP_COUNT, false);
@@ -190,6 +240,10 @@ impl NormalAggrObj for AggrGroupCount {
                 .collect(),
         ))
     }
+
+    fn buffered(&self) -> usize {
+        self.accum.len()
+    }
 }
 
 define_aggr!(AGGR_COUNT_UNIQUE, false);
@@ -212,6 +266,10 @@ impl NormalAggrObj for AggrCountUnique {
     fn get(&self) -> Result<DataValue> {
         Ok(DataValue::from(self.count))
     }
+
+    fn buffered(&self) -> usize {
+        self.accum.len()
+    }
 }
 
 define_aggr!(AGGR_UNION, true);
@@ -233,6 +291,10 @@ impl NormalAggrObj for AggrUnion {
     fn get(&self) -> Result<DataValue> {
         Ok(DataValue::List(self.accum.iter().cloned().collect()))
     }
+
+    fn buffered(&self) -> usize {
+        self.accum.len()
+    }
 }
 
 pub(crate) struct MeetAggrUnion;
@@ -302,6 +364,10 @@ impl NormalAggrObj for AggrIntersection {
             Some(l) => Ok(DataValue::List(l.iter().cloned().collect())),
         }
     }
+
+    fn buffered(&self) -> usize {
+        self.accum.as_ref().map_or(0, |accum| accum.len())
+    }
 }
 
 pub(crate) struct MeetAggrIntersection;
@@ -383,6 +449,36 @@ impl NormalAggrObj for AggrCollect {
     fn get(&self) -> Result<DataValue> {
         Ok(DataValue::List(self.accum.clone()))
     }
+
+    fn buffered(&self) -> usize {
+        self.accum.len()
+    }
+}
+
+define_aggr!(AGGR_FLATTEN, false);
+
+#[derive(Default)]
+pub(crate) struct AggrFlatten {
+    accum: Vec<DataValue>,
+}
+
+impl NormalAggrObj for AggrFlatten {
+    fn set(&mut self, value: &DataValue) -> Result<()> {
+        match value {
+            DataValue::List(l) => self.accum.extend_from_slice(l),
+            DataValue::Set(s) => self.accum.extend(s.iter().cloned()),
+            v => bail!("cannot compute 'flatten' for value {:?}", v),
+        }
+        Ok(())
+    }
+
+    fn get(&self) -> Result<DataValue> {
+        Ok(DataValue::List(self.accum.clone()))
+    }
+
+    fn buffered(&self) -> usize {
+        self.accum.len()
+    }
 }
 
 define_aggr!(AGGR_CHOICE_RAND, false);
@@ -435,6 +531,26 @@ impl NormalAggrObj for AggrCount {
     }
 }
 
+define_aggr!(AGGR_COUNT_NON_NULL, false);
+
+#[derive(Default)]
+pub(crate) struct AggrCountNonNull {
+    count: i64,
+}
+
+impl NormalAggrObj for AggrCountNonNull {
+    fn set(&mut self, value: &DataValue) -> Result<()> {
+        if *value != DataValue::Null {
+            self.count += 1;
+        }
+        Ok(())
+    }
+
+    fn get(&self) -> Result<DataValue> {
+        Ok(DataValue::from(self.count))
+    }
+}
+
 define_aggr!(AGGR_VARIANCE, false);
 
 #[derive(Default)]
@@ -496,6 +612,63 @@ impl NormalAggrObj for AggrStdDev {
     }
 }
 
+define_aggr!(AGGR_POP_VARIANCE, false);
+
+#[derive(Default)]
+pub(crate) struct AggrPopVariance {
+    count: i64,
+    sum: f64,
+    sum_sq: f64,
+}
+
+impl AggrPopVariance {
+    fn variance(&self) -> f64 {
+        let ct = self.count as f64;
+        let var = (self.sum_sq - self.sum * self.sum / ct) / ct;
+        // guard against tiny negative results from rounding, e.g. for a single value
+        var.max(0.)
+    }
+}
+
+impl NormalAggrObj for AggrPopVariance {
+    fn set(&mut self, value: &DataValue) -> Result<()> {
+        match value {
+            DataValue::Num(n) => {
+                let f = n.get_float();
+                self.sum += f;
+                self.sum_sq += f * f;
+                self.count += 1;
+            }
+            v => bail!("cannot compute 'pop_variance': encountered value {:?}", v),
+        }
+        Ok(())
+    }
+
+    fn get(&self) -> Result<DataValue> {
+        Ok(DataValue::from(self.variance()))
+    }
+}
+
+define_aggr!(AGGR_POP_STD_DEV, false);
+
+#[derive(Default)]
+pub(crate) struct AggrPopStdDev {
+    inner: AggrPopVariance,
+}
+
+impl NormalAggrObj for AggrPopStdDev {
+    fn set(&mut self, value: &DataValue) -> Result<()> {
+        match value {
+            DataValue::Num(_) => self.inner.set(value),
+            v => bail!("cannot compute 'pop_std_dev': encountered value {:?}", v),
+        }
+    }
+
+    fn get(&self) -> Result<DataValue> {
+        Ok(DataValue::from(self.inner.variance().sqrt()))
+    }
+}
+
 define_aggr!(AGGR_MEAN, false);
 
 #[derive(Default)]
@@ -517,22 +690,36 @@ impl NormalAggrObj for AggrMean {
     }
 
     fn get(&self) -> Result<DataValue> {
+        if self.count == 0 {
+            return Ok(DataValue::Null);
+        }
         Ok(DataValue::from(self.sum / (self.count as f64)))
     }
 }
 
 define_aggr!(AGGR_SUM, false);
 
-#[derive(Default)]
 pub(crate) struct AggrSum {
-    sum: f64,
+    sum: Num,
+}
+
+impl Default for AggrSum {
+    fn default() -> Self {
+        Self { sum: Num::Int(0) }
+    }
 }
 
 impl NormalAggrObj for AggrSum {
     fn set(&mut self, value: &DataValue) -> Result<()> {
         match value {
             DataValue::Num(n) => {
-                self.sum += n.get_float();
+                self.sum = match (self.sum, *n) {
+                    (Num::Int(a), Num::Int(b)) => match a.checked_add(b) {
+                        Some(sum) => Num::Int(sum),
+                        None => Num::Float(a as f64 + b as f64),
+                    },
+                    (a, b) => Num::Float(a.get_float() + b.get_float()),
+                };
             }
             v => bail!("cannot compute 'sum': encountered value {:?}", v),
         }
@@ -540,7 +727,7 @@ impl NormalAggrObj for AggrSum {
     }
 
     fn get(&self) -> Result<DataValue> {
-        Ok(DataValue::from(self.sum))
+        Ok(DataValue::Num(self.sum))
     }
 }
 
@@ -1164,16 +1351,20 @@ pub(crate) fn parse_aggr(name: &str) -> Option<&'static Aggregation> {
         "union" => &AGGR_UNION,
         "intersection" => &AGGR_INTERSECTION,
         "count" => &AGGR_COUNT,
+        "count_non_null" => &AGGR_COUNT_NON_NULL,
         "count_unique" => &AGGR_COUNT_UNIQUE,
         "variance" => &AGGR_VARIANCE,
         "std_dev" => &AGGR_STD_DEV,
+        "pop_variance" => &AGGR_POP_VARIANCE,
+        "pop_std_dev" => &AGGR_POP_STD_DEV,
         "sum" => &AGGR_SUM,
         "product" => &AGGR_PRODUCT,
         "min" => &AGGR_MIN,
         "max" => &AGGR_MAX,
-        "mean" => &AGGR_MEAN,
+        "mean" | "avg" => &AGGR_MEAN,
         "choice" => &AGGR_CHOICE,
         "collect" => &AGGR_COLLECT,
+        "flatten" => &AGGR_FLATTEN,
         "shortest" => &AGGR_SHORTEST,
         "min_cost" => &AGGR_MIN_COST,
         "bit_and" => &AGGR_BIT_AND,
@@ -1210,6 +1401,7 @@ impl Aggregation {
             name if name == AGGR_AND.name => Box::new(AggrAnd::default()),
             name if name == AGGR_OR.name => Box::new(AggrOr::default()),
             name if name == AGGR_COUNT.name => Box::new(AggrCount::default()),
+            name if name == AGGR_COUNT_NON_NULL.name => Box::new(AggrCountNonNull::default()),
             name if name == AGGR_GROUP_COUNT.name => Box::new(AggrGroupCount::default()),
             name if name == AGGR_COUNT_UNIQUE.name => Box::new(AggrCountUnique::default()),
             name if name == AGGR_SUM.name => Box::new(AggrSum::default()),
@@ -1219,6 +1411,8 @@ impl Aggregation {
             name if name == AGGR_MEAN.name => Box::new(AggrMean::default()),
             name if name == AGGR_VARIANCE.name => Box::new(AggrVariance::default()),
             name if name == AGGR_STD_DEV.name => Box::new(AggrStdDev::default()),
+            name if name == AGGR_POP_VARIANCE.name => Box::new(AggrPopVariance::default()),
+            name if name == AGGR_POP_STD_DEV.name => Box::new(AggrPopStdDev::default()),
             name if name == AGGR_CHOICE.name => Box::new(AggrChoice::default()),
             name if name == AGGR_BIT_AND.name => Box::new(AggrBitAnd::default()),
             name if name == AGGR_BIT_OR.name => Box::new(AggrBitOr::default()),
@@ -1231,6 +1425,7 @@ impl Aggregation {
             name if name == AGGR_LATEST_BY.name => Box::new(AggrLatestBy::default()),
             name if name == AGGR_SMALLEST_BY.name => Box::new(AggrSmallestBy::default()),
             name if name == AGGR_CHOICE_RAND.name => Box::new(AggrChoiceRand::default()),
+            name if name == AGGR_FLATTEN.name => Box::new(AggrFlatten::default()),
             name if name == AGGR_COLLECT.name => Box::new({
                 if args.is_empty() {
                     AggrCollect::default()
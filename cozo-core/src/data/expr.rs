@@ -574,6 +574,26 @@ impl Expr {
                     }
                     ValueRange::default()
                 }
+                n if n == OP_BETWEEN.name || n == OP_BETWEEN_EXCLUSIVE.name => {
+                    if let Some(symb) = args[0].get_binding() {
+                        if let (Some(lower), Some(upper)) =
+                            (args[1].get_const(), args[2].get_const())
+                        {
+                            if target == symb {
+                                let lower = match lower.get_int() {
+                                    Some(i) => DataValue::from(i),
+                                    None => lower.clone(),
+                                };
+                                let upper = match upper.get_float() {
+                                    Some(f) => DataValue::from(f),
+                                    None => upper.clone(),
+                                };
+                                return Ok(ValueRange::new(lower, upper));
+                            }
+                        }
+                    }
+                    ValueRange::default()
+                }
                 n if n == OP_STARTS_WITH.name => {
                     if let Some(symb) = args[0].get_binding() {
                         if let Some(val) = args[1].get_const() {
@@ -813,6 +833,7 @@ pub(crate) fn get_op(name: &str) -> Option<&'static Op> {
         "ceil" => &OP_CEIL,
         "round" => &OP_ROUND,
         "mod" => &OP_MOD,
+        "bucket" => &OP_BUCKET,
         "max" => &OP_MAX,
         "min" => &OP_MIN,
         "pow" => &OP_POW,
@@ -841,6 +862,8 @@ pub(crate) fn get_op(name: &str) -> Option<&'static Op> {
         "ge" => &OP_GE,
         "lt" => &OP_LT,
         "le" => &OP_LE,
+        "between" => &OP_BETWEEN,
+        "between_exclusive" => &OP_BETWEEN_EXCLUSIVE,
         "or" => &OP_OR,
         "and" => &OP_AND,
         "negate" => &OP_NEGATE,
@@ -379,6 +379,24 @@ pub(crate) fn op_le(args: &[DataValue]) -> Result<DataValue> {
     }))
 }
 
+define_op!(OP_BETWEEN, 3, false);
+pub(crate) fn op_between(args: &[DataValue]) -> Result<DataValue> {
+    let above = op_ge(&args[..2])?;
+    let below = op_le(&[args[0].clone(), args[2].clone()])?;
+    Ok(DataValue::from(
+        above == DataValue::from(true) && below == DataValue::from(true),
+    ))
+}
+
+define_op!(OP_BETWEEN_EXCLUSIVE, 3, false);
+pub(crate) fn op_between_exclusive(args: &[DataValue]) -> Result<DataValue> {
+    let above = op_gt(&args[..2])?;
+    let below = op_lt(&[args[0].clone(), args[2].clone()])?;
+    Ok(DataValue::from(
+        above == DataValue::from(true) && below == DataValue::from(true),
+    ))
+}
+
 define_op!(OP_ADD, 0, true);
 pub(crate) fn op_add(args: &[DataValue]) -> Result<DataValue> {
     let mut i_accum = 0i64;
@@ -1088,6 +1106,27 @@ pub(crate) fn op_mod(args: &[DataValue]) -> Result<DataValue> {
     })
 }
 
+define_op!(OP_BUCKET, 4, false);
+pub(crate) fn op_bucket(args: &[DataValue]) -> Result<DataValue> {
+    let (val, min, max, width) = match (
+        args[0].get_float(),
+        args[1].get_float(),
+        args[2].get_float(),
+        args[3].get_float(),
+    ) {
+        (Some(val), Some(min), Some(max), Some(width)) => (val, min, max, width),
+        _ => bail!("'bucket' requires numbers"),
+    };
+    if width <= 0. || max <= min {
+        bail!("'bucket' requires a positive width and 'max' greater than 'min'")
+    }
+    // buckets cover `[min, max)`, values outside have no bucket
+    if val < min || val >= max {
+        return Ok(DataValue::Null);
+    }
+    Ok(DataValue::from(((val - min) / width).floor() as i64))
+}
+
 define_op!(OP_AND, 0, true);
 pub(crate) fn op_and(args: &[DataValue]) -> Result<DataValue> {
     for arg in args {
@@ -1253,17 +1292,6 @@ pub(crate) fn op_pack_bits(args: &[DataValue]) -> Result<DataValue> {
 define_op!(OP_CONCAT, 1, true);
 pub(crate) fn op_concat(args: &[DataValue]) -> Result<DataValue> {
     match &args[0] {
-        DataValue::Str(_) => {
-            let mut ret: String = Default::default();
-            for arg in args {
-                if let DataValue::Str(s) = arg {
-                    ret += s;
-                } else {
-                    bail!("'concat' requires strings, or lists");
-                }
-            }
-            Ok(DataValue::from(ret))
-        }
         DataValue::List(_) | DataValue::Set(_) => {
             let mut ret = vec![];
             for arg in args {
@@ -1272,23 +1300,50 @@ pub(crate) fn op_concat(args: &[DataValue]) -> Result<DataValue> {
                 } else if let DataValue::Set(s) = arg {
                     ret.extend(s.iter().cloned());
                 } else {
-                    bail!("'concat' requires strings, or lists");
+                    bail!("'concat' cannot join a list with {}", arg);
                 }
             }
             Ok(DataValue::List(ret))
         }
+        DataValue::Bytes(_) if !args.iter().any(|arg| matches!(arg, DataValue::Str(_))) => {
+            let mut ret = vec![];
+            for arg in args {
+                match arg {
+                    DataValue::Bytes(b) => ret.extend_from_slice(b),
+                    _ => bail!("'concat' cannot join bytes with {}", arg),
+                }
+            }
+            Ok(DataValue::Bytes(ret))
+        }
         DataValue::Json(_) => {
             let mut ret = json!(null);
             for arg in args {
                 if let DataValue::Json(j) = arg {
                     ret = deep_merge_json(ret, j.0.clone());
                 } else {
-                    bail!("'concat' requires strings, lists, or JSON objects");
+                    bail!("'concat' cannot join a JSON object with {}", arg);
                 }
             }
             Ok(DataValue::Json(JsonData(ret)))
         }
-        _ => bail!("'concat' requires strings, lists, or JSON objects"),
+        // as long as one operand is a string, other scalars are coerced as by `to_string`;
+        // lists and JSON objects are refused wherever they appear, as when they come first
+        _ if args.iter().any(|arg| matches!(arg, DataValue::Str(_))) => {
+            let mut ret: String = Default::default();
+            for arg in args {
+                match arg {
+                    DataValue::List(_) | DataValue::Set(_) => {
+                        bail!("'concat' cannot join a list with {}", args[0])
+                    }
+                    DataValue::Json(_) => {
+                        bail!("'concat' cannot join a JSON object with {}", args[0])
+                    }
+                    _ => ret += &val2str(arg),
+                }
+            }
+            Ok(DataValue::from(ret))
+        }
+        _ => bail!("'concat' requires strings, lists, bytes, or JSON objects"),
     }
 }
 
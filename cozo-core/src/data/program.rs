@@ -16,7 +16,7 @@ use smallvec::SmallVec;
 use smartstring::{LazyCompact, SmartString};
 use thiserror::Error;
 
-use crate::data::aggr::Aggregation;
+use crate::data::aggr::{AggrLimits, Aggregation};
 use crate::data::expr::Expr;
 use crate::data::relation::StoredRelationMetadata;
 use crate::data::symb::{Symbol, PROG_ENTRY};
@@ -52,9 +52,11 @@ pub(crate) struct QueryOutOptions {
     pub(crate) offset: Option<usize>,
     pub(crate) timeout: Option<f64>,
     pub(crate) sleep: Option<f64>,
-    pub(crate) sorters: Vec<(Symbol, SortDir)>,
+    pub(crate) sorters: Vec<(Symbol, SortDir, Collation)>,
     pub(crate) store_relation: Option<(InputRelationHandle, RelationOp, ReturnMutation)>,
     pub(crate) assertion: Option<QueryAssertion>,
+    pub(crate) max_group_values: Option<usize>,
+    pub(crate) max_aggr_values: Option<usize>,
 }
 
 impl Debug for QueryOutOptions {
@@ -74,12 +76,22 @@ impl Display for QueryOutOptions {
         if let Some(l) = self.timeout {
             writeln!(f, ":timeout {l};")?;
         }
-        for (symb, dir) in &self.sorters {
+        if let Some(l) = self.max_group_values {
+            writeln!(f, ":max_group_values {l};")?;
+        }
+        if let Some(l) = self.max_aggr_values {
+            writeln!(f, ":max_aggr_values {l};")?;
+        }
+        for (symb, dir, collation) in &self.sorters {
             write!(f, ":order ")?;
             if *dir == SortDir::Dsc {
                 write!(f, "-")?;
             }
-            writeln!(f, "{symb};")?;
+            write!(f, "{symb}")?;
+            if *collation == Collation::Unicode {
+                write!(f, " collate unicode")?;
+            }
+            writeln!(f, ";")?;
         }
         if let Some((
                         InputRelationHandle {
@@ -174,6 +186,12 @@ impl Display for QueryOutOptions {
 }
 
 impl QueryOutOptions {
+    pub(crate) fn aggr_limits(&self) -> AggrLimits {
+        AggrLimits {
+            per_group: self.max_group_values,
+            total: self.max_aggr_values,
+        }
+    }
     pub(crate) fn num_to_take(&self) -> Option<usize> {
         match (self.limit, self.offset) {
             (None, _) => None,
@@ -189,6 +207,15 @@ pub(crate) enum SortDir {
     Dsc,
 }
 
+/// How strings are compared when sorting outputs
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum Collation {
+    /// Code point order, the same as the storage order
+    Binary,
+    /// Accents and case only break ties between otherwise equal letters
+    Unicode,
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub(crate) enum RelationOp {
     Create,
@@ -465,6 +492,7 @@ pub(crate) struct InputProgram {
     pub(crate) prog: BTreeMap<Symbol, InputInlineRulesOrFixed>,
     pub(crate) out_opts: QueryOutOptions,
     pub(crate) disable_magic_rewrite: bool,
+    pub(crate) lenient: bool,
 }
 
 impl Display for InputProgram {
@@ -641,8 +669,13 @@ impl InputProgram {
                             counter += 1;
                             Symbol::new(&format!("***{counter}") as &str, span)
                         };
+                        let body = if self.lenient {
+                            InputAtom::empty_missing_relations(rule.body, tx)?
+                        } else {
+                            rule.body
+                        };
                         let normalized_body = InputAtom::Conjunction {
-                            inner: rule.body,
+                            inner: InputAtom::expand_optional_atoms(body, tx)?,
                             span: rule.span,
                         }
                             .disjunctive_normal_form(tx)?;
@@ -940,6 +973,10 @@ pub(crate) enum InputAtom {
         inner: Box<InputAtom>,
         span: SourceSpan,
     },
+    Optional {
+        inner: Box<InputAtom>,
+        span: SourceSpan,
+    },
     Conjunction {
         inner: Vec<InputAtom>,
         span: SourceSpan,
@@ -1652,6 +1689,9 @@ impl Display for InputAtom {
             InputAtom::Negation { inner, .. } => {
                 write!(f, "not {inner}")?;
             }
+            InputAtom::Optional { inner, .. } => {
+                write!(f, "optional {inner}")?;
+            }
             InputAtom::Conjunction { inner, .. } => {
                 for (i, a) in inner.iter().enumerate() {
                     if i > 0 {
@@ -1704,6 +1744,7 @@ impl InputAtom {
     pub(crate) fn span(&self) -> SourceSpan {
         match self {
             InputAtom::Negation { span, .. }
+            | InputAtom::Optional { span, .. }
             | InputAtom::Conjunction { span, .. }
             | InputAtom::Disjunction { span, .. } => *span,
             InputAtom::Rule { inner, .. } => inner.span,
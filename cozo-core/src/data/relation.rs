@@ -22,6 +22,7 @@ use thiserror::Error;
 
 use crate::data::expr::Expr;
 use crate::data::value::{DataValue, JsonData, UuidWrapper, Validity, ValidityTs, Vector};
+use crate::parse::{is_identifier, parse_expressions, Rule};
 use crate::Num;
 
 #[derive(Debug, Clone, Eq, PartialEq, serde_derive::Deserialize, serde_derive::Serialize)]
@@ -115,6 +116,8 @@ pub(crate) struct ColumnDef {
     pub(crate) name: SmartString<LazyCompact>,
     pub(crate) typing: NullableColType,
     pub(crate) default_gen: Option<Expr>,
+    #[serde(default)]
+    pub(crate) description: SmartString<LazyCompact>,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, serde_derive::Deserialize, serde_derive::Serialize)]
@@ -455,3 +458,163 @@ impl NullableColType {
         })
     }
 }
+
+#[derive(Debug, Error, Diagnostic)]
+#[error("'{1}' is not a valid {0} name")]
+#[diagnostic(code(parser::bad_schema_name))]
+struct BadSchemaName(&'static str, String);
+
+/// A column of a [`RelationDef`], built up with typed methods instead of DDL text.
+/// Columns without a declared type have type `Any?`, as in `:create`.
+#[derive(Debug, Clone)]
+pub struct ColumnSpec {
+    name: String,
+    coltype: Option<ColType>,
+    nullable: bool,
+    default: Option<String>,
+    description: Option<String>,
+}
+
+impl ColumnSpec {
+    /// A column named `name` of type `Any?`. Fails if `name` is not an identifier.
+    pub fn new(name: impl Into<String>) -> Result<Self> {
+        let name = name.into();
+        ensure!(
+            is_identifier(Rule::ident, &name),
+            BadSchemaName("column", name)
+        );
+        Ok(Self {
+            name,
+            coltype: None,
+            nullable: false,
+            default: None,
+            description: None,
+        })
+    }
+    fn coltype(mut self, coltype: ColType) -> Self {
+        self.coltype = Some(coltype);
+        self
+    }
+    fn typing(&self) -> NullableColType {
+        match &self.coltype {
+            None => NullableColType {
+                coltype: ColType::Any,
+                nullable: true,
+            },
+            Some(coltype) => NullableColType {
+                coltype: coltype.clone(),
+                nullable: self.nullable,
+            },
+        }
+    }
+    /// Declare the column as `Bool`
+    pub fn bool(self) -> Self {
+        self.coltype(ColType::Bool)
+    }
+    /// Declare the column as `Int`
+    pub fn int(self) -> Self {
+        self.coltype(ColType::Int)
+    }
+    /// Declare the column as `Float`
+    pub fn float(self) -> Self {
+        self.coltype(ColType::Float)
+    }
+    /// Declare the column as `String`
+    pub fn string(self) -> Self {
+        self.coltype(ColType::String)
+    }
+    /// Declare the column as `Bytes`
+    pub fn bytes(self) -> Self {
+        self.coltype(ColType::Bytes)
+    }
+    /// Declare the column as `Uuid`
+    pub fn uuid(self) -> Self {
+        self.coltype(ColType::Uuid)
+    }
+    /// Declare the column as `Json`
+    pub fn json(self) -> Self {
+        self.coltype(ColType::Json)
+    }
+    /// Declare the column as `Validity`, which must be the last key column
+    pub fn validity(self) -> Self {
+        self.coltype(ColType::Validity)
+    }
+    /// Allow nulls in the column. Columns without a declared type always allow nulls.
+    pub fn nullable(mut self) -> Self {
+        self.nullable = true;
+        self
+    }
+    /// Fill the column with the CozoScript expression `expr` when a write omits it.
+    /// Fails if `expr` does not parse as an expression.
+    pub fn default(mut self, expr: impl Into<String>) -> Result<Self> {
+        let expr = expr.into();
+        parse_expressions(&expr, &Default::default())?;
+        self.default = Some(expr);
+        Ok(self)
+    }
+    /// Describe the column, as shown by `::columns`
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+}
+
+impl Display for ColumnSpec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.name, self.typing())?;
+        if let Some(description) = &self.description {
+            write!(f, " {}", json!(description))?;
+        }
+        if let Some(default) = &self.default {
+            write!(f, " default {default}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A stored relation schema for [`Db::create_relation`](crate::Db::create_relation),
+/// equivalent to the schema of a `:create` query.
+#[derive(Debug, Clone)]
+pub struct RelationDef {
+    name: String,
+    keys: Vec<ColumnSpec>,
+    non_keys: Vec<ColumnSpec>,
+}
+
+impl RelationDef {
+    /// A relation named `name` with no columns yet. Fails if `name` is not
+    /// a valid relation name.
+    pub fn new(name: impl Into<String>) -> Result<Self> {
+        let name = name.into();
+        ensure!(
+            is_identifier(Rule::compound_ident, &name)
+                || is_identifier(Rule::underscore_ident, &name),
+            BadSchemaName("relation", name)
+        );
+        Ok(Self {
+            name,
+            keys: vec![],
+            non_keys: vec![],
+        })
+    }
+    /// Append a key column
+    pub fn key(mut self, column: ColumnSpec) -> Self {
+        self.keys.push(column);
+        self
+    }
+    /// Append a non-key column
+    pub fn column(mut self, column: ColumnSpec) -> Self {
+        self.non_keys.push(column);
+        self
+    }
+}
+
+impl Display for RelationDef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, ":create {} {{{}", self.name, self.keys.iter().join(", "))?;
+        if !self.non_keys.is_empty() {
+            write!(f, " => {}", self.non_keys.iter().join(", "))?;
+        }
+        f.write_str("}")
+    }
+}
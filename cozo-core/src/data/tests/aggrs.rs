@@ -233,6 +233,32 @@ fn test_collect() {
     );
 }
 
+#[test]
+fn test_flatten() {
+    let mut aggr = parse_aggr("flatten").unwrap().clone();
+    aggr.normal_init(&[]).unwrap();
+
+    let mut flatten_aggr = aggr.normal_op.unwrap();
+    flatten_aggr
+        .set(&DataValue::List(vec![
+            DataValue::from(1),
+            DataValue::from(2),
+        ]))
+        .unwrap();
+    flatten_aggr.set(&DataValue::List(vec![])).unwrap();
+    flatten_aggr
+        .set(&DataValue::List(vec![
+            DataValue::from(2),
+            DataValue::from(1),
+        ]))
+        .unwrap();
+    assert_eq!(
+        flatten_aggr.get().unwrap(),
+        DataValue::List([1, 2, 2, 1].into_iter().map(DataValue::from).collect())
+    );
+    assert!(flatten_aggr.set(&DataValue::from(3)).is_err());
+}
+
 #[test]
 fn test_count() {
     let mut aggr = parse_aggr("count").unwrap().clone();
@@ -248,6 +274,20 @@ fn test_count() {
     assert_eq!(count_aggr.get().unwrap(), DataValue::from(6));
 }
 
+#[test]
+fn test_count_non_null() {
+    let mut aggr = parse_aggr("count_non_null").unwrap().clone();
+    aggr.normal_init(&[]).unwrap();
+
+    let mut count_aggr = aggr.normal_op.unwrap();
+    count_aggr.set(&DataValue::Null).unwrap();
+    count_aggr.set(&DataValue::Null).unwrap();
+    count_aggr.set(&DataValue::from(true)).unwrap();
+    count_aggr.set(&DataValue::from(false)).unwrap();
+    count_aggr.set(&DataValue::from(0)).unwrap();
+    assert_eq!(count_aggr.get().unwrap(), DataValue::from(3));
+}
+
 #[test]
 fn test_variance() {
     let mut aggr = parse_aggr("variance").unwrap().clone();
@@ -271,6 +311,43 @@ fn test_std_dev() {
     assert!(v.abs_diff_eq(&(0.5_f64).sqrt(), 1e-10));
 }
 
+#[test]
+fn test_pop_variance() {
+    let mut aggr = parse_aggr("pop_variance").unwrap().clone();
+    aggr.normal_init(&[]).unwrap();
+
+    let mut variance_aggr = aggr.normal_op.unwrap();
+    for v in [2, 4, 4, 4, 5, 5, 7, 9] {
+        variance_aggr.set(&DataValue::from(v)).unwrap();
+    }
+    assert_eq!(variance_aggr.get().unwrap(), DataValue::from(4.));
+
+    let mut aggr = parse_aggr("pop_variance").unwrap().clone();
+    aggr.normal_init(&[]).unwrap();
+    let mut single_aggr = aggr.normal_op.unwrap();
+    single_aggr.set(&DataValue::from(0.1)).unwrap();
+    assert_eq!(single_aggr.get().unwrap(), DataValue::from(0.));
+}
+
+#[test]
+fn test_pop_std_dev() {
+    let mut aggr = parse_aggr("pop_std_dev").unwrap().clone();
+    aggr.normal_init(&[]).unwrap();
+
+    let mut std_dev_aggr = aggr.normal_op.unwrap();
+    for v in [2, 4, 4, 4, 5, 5, 7, 9] {
+        std_dev_aggr.set(&DataValue::from(v)).unwrap();
+    }
+    assert_eq!(std_dev_aggr.get().unwrap(), DataValue::from(2.));
+    assert!(std_dev_aggr.set(&DataValue::from("a")).is_err());
+
+    let mut aggr = parse_aggr("pop_std_dev").unwrap().clone();
+    aggr.normal_init(&[]).unwrap();
+    let mut single_aggr = aggr.normal_op.unwrap();
+    single_aggr.set(&DataValue::from(542)).unwrap();
+    assert_eq!(single_aggr.get().unwrap(), DataValue::from(0.));
+}
+
 #[test]
 fn test_mean() {
     let mut aggr = parse_aggr("mean").unwrap().clone();
@@ -285,6 +362,18 @@ fn test_mean() {
     assert_eq!(mean_aggr.get().unwrap(), DataValue::from(3.));
 }
 
+#[test]
+fn test_avg() {
+    let mut aggr = parse_aggr("avg").unwrap().clone();
+    aggr.normal_init(&[]).unwrap();
+
+    let mut avg_aggr = aggr.normal_op.unwrap();
+    assert_eq!(avg_aggr.get().unwrap(), DataValue::Null);
+    avg_aggr.set(&DataValue::from(1)).unwrap();
+    avg_aggr.set(&DataValue::from(2)).unwrap();
+    assert_eq!(avg_aggr.get().unwrap(), DataValue::from(1.5));
+}
+
 #[test]
 fn test_sum() {
     let mut aggr = parse_aggr("sum").unwrap().clone();
@@ -296,7 +385,10 @@ fn test_sum() {
     sum_aggr.set(&DataValue::from(3)).unwrap();
     sum_aggr.set(&DataValue::from(4)).unwrap();
     sum_aggr.set(&DataValue::from(5)).unwrap();
-    assert_eq!(sum_aggr.get().unwrap(), DataValue::from(15.));
+    assert_eq!(sum_aggr.get().unwrap(), DataValue::from(15));
+    sum_aggr.set(&DataValue::from(0.5)).unwrap();
+    assert_eq!(sum_aggr.get().unwrap(), DataValue::from(15.5));
+    assert!(sum_aggr.set(&DataValue::from("a")).is_err());
 }
 
 #[test]
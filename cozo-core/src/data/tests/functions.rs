@@ -12,7 +12,7 @@ use regex::Regex;
 use serde_json::json;
 
 use crate::data::functions::*;
-use crate::data::value::{DataValue, RegexWrapper};
+use crate::data::value::{DataValue, JsonData, RegexWrapper};
 use crate::DbInstance;
 
 #[test]
@@ -249,6 +249,42 @@ fn test_comparators() {
     assert!(op_lt(&[DataValue::Null, DataValue::from(true)]).is_err());
 }
 
+#[test]
+fn test_between() {
+    assert_eq!(
+        op_between(&[DataValue::from(6), DataValue::from(6), DataValue::from(10)]).unwrap(),
+        DataValue::from(true)
+    );
+    assert_eq!(
+        op_between(&[
+            DataValue::from(10.),
+            DataValue::from(6),
+            DataValue::from(10)
+        ])
+        .unwrap(),
+        DataValue::from(true)
+    );
+    assert_eq!(
+        op_between(&[DataValue::from(11), DataValue::from(6), DataValue::from(10)]).unwrap(),
+        DataValue::from(false)
+    );
+    assert_eq!(
+        op_between_exclusive(&[DataValue::from(6), DataValue::from(6), DataValue::from(10)])
+            .unwrap(),
+        DataValue::from(false)
+    );
+    assert_eq!(
+        op_between_exclusive(&[DataValue::from(7), DataValue::from(6), DataValue::from(10)])
+            .unwrap(),
+        DataValue::from(true)
+    );
+    assert!(op_between(&[
+        DataValue::from(6),
+        DataValue::from("a"),
+        DataValue::from(10)
+    ])
+    .is_err());
+}
 #[test]
 fn test_max_min() {
     assert_eq!(op_max(&[DataValue::from(1),]).unwrap(), DataValue::from(1));
@@ -545,6 +581,32 @@ fn test_mod() {
     assert!(op_mod(&[DataValue::from(5), DataValue::from(0)]).is_err());
 }
 
+#[test]
+fn test_bucket() {
+    let bucket = |v: DataValue| {
+        op_bucket(&[
+            v,
+            DataValue::from(0),
+            DataValue::from(5000),
+            DataValue::from(500),
+        ])
+    };
+    assert_eq!(bucket(DataValue::from(0)).unwrap(), DataValue::from(0));
+    assert_eq!(bucket(DataValue::from(499.9)).unwrap(), DataValue::from(0));
+    assert_eq!(bucket(DataValue::from(542)).unwrap(), DataValue::from(1));
+    assert_eq!(bucket(DataValue::from(4999)).unwrap(), DataValue::from(9));
+    assert_eq!(bucket(DataValue::from(5000)).unwrap(), DataValue::Null);
+    assert_eq!(bucket(DataValue::from(-1)).unwrap(), DataValue::Null);
+    assert!(bucket(DataValue::from("a")).is_err());
+    assert!(op_bucket(&[
+        DataValue::from(1),
+        DataValue::from(0),
+        DataValue::from(10),
+        DataValue::from(0)
+    ])
+    .is_err());
+}
+
 #[test]
 fn test_boolean() {
     assert_eq!(op_and(&[]).unwrap(), DataValue::from(true));
@@ -622,6 +684,21 @@ fn test_concat() {
         op_concat(&[DataValue::Str("abc".into()), DataValue::Str("def".into())]).unwrap(),
         DataValue::Str("abcdef".into())
     );
+    assert_eq!(
+        op_concat(&[DataValue::Str("runway-".into()), DataValue::from(3)]).unwrap(),
+        DataValue::Str("runway-3".into())
+    );
+    assert_eq!(
+        op_concat(&[
+            DataValue::from(1.5),
+            DataValue::Str("/".into()),
+            DataValue::from(true),
+            DataValue::Null
+        ])
+        .unwrap(),
+        DataValue::Str("1.5/truenull".into())
+    );
+    assert!(op_concat(&[DataValue::from(1), DataValue::from(2)]).is_err());
 
     assert_eq!(
         op_concat(&[
@@ -635,6 +712,48 @@ fn test_concat() {
             DataValue::from(true),
         ])
     );
+    let err = op_concat(&[
+        DataValue::List(vec![DataValue::from(1)]),
+        DataValue::Str("a".into()),
+    ])
+    .unwrap_err();
+    assert!(err.to_string().contains("cannot join a list"), "{err}");
+    let err = op_concat(&[
+        DataValue::Str("a".into()),
+        DataValue::List(vec![DataValue::from(1)]),
+    ])
+    .unwrap_err();
+    assert!(err.to_string().contains("cannot join a list"), "{err}");
+    let err = op_concat(&[
+        DataValue::Json(JsonData(json!({"a": 1}))),
+        DataValue::Str("a".into()),
+    ])
+    .unwrap_err();
+    assert!(
+        err.to_string().contains("cannot join a JSON object"),
+        "{err}"
+    );
+    let err = op_concat(&[
+        DataValue::Str("a".into()),
+        DataValue::Json(JsonData(json!({"a": 1}))),
+    ])
+    .unwrap_err();
+    assert!(
+        err.to_string().contains("cannot join a JSON object"),
+        "{err}"
+    );
+
+    assert_eq!(
+        op_concat(&[
+            DataValue::Bytes(vec![1, 2]),
+            DataValue::Bytes(vec![]),
+            DataValue::Bytes(vec![3])
+        ])
+        .unwrap(),
+        DataValue::Bytes(vec![1, 2, 3])
+    );
+    let err = op_concat(&[DataValue::Bytes(vec![1]), DataValue::from(2)]).unwrap_err();
+    assert!(err.to_string().contains("cannot join bytes"), "{err}");
 }
 
 #[test]
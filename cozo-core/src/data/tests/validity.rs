@@ -7,11 +7,26 @@
  *
  */
 
-use crate::data::value::DataValue;
+use crate::data::value::{DataValue, ValidityTs};
 use crate::DbInstance;
 use serde_json::json;
 use std::env;
 
+#[test]
+fn test_validity_ts() {
+    let past = ValidityTs::from_micros(1_600_000_000_000_000);
+    assert_eq!(past.as_micros(), 1_600_000_000_000_000);
+    assert_eq!(ValidityTs::from_micros(past.as_micros()), past);
+
+    let now = ValidityTs::now();
+    assert!(now.is_after(&past));
+    assert!(!past.is_after(&now));
+    // storage order puts later timestamps first
+    assert!(now < past);
+    assert!(ValidityTs::LATEST.is_after(&now) && past.is_after(&ValidityTs::EARLIEST));
+    assert!(ValidityTs::LATEST < now && past < ValidityTs::EARLIEST);
+}
+
 #[test]
 fn test_validity() {
     let path = "_test_validity";
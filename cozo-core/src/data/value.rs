@@ -15,6 +15,7 @@ use std::fmt::{Debug, Display, Formatter};
 use std::hash::{Hash, Hasher};
 use std::ops::Deref;
 
+use crate::data::functions::current_validity;
 use crate::data::json::JsonValue;
 use crate::data::relation::VecElementType;
 use ordered_float::OrderedFloat;
@@ -96,7 +97,8 @@ impl PartialOrd for RegexWrapper {
     }
 }
 
-/// Timestamp part of validity
+/// Timestamp part of validity, in microseconds since the UNIX epoch.
+/// Ordering follows storage order, which puts later timestamps first.
 #[derive(
 Copy,
 Clone,
@@ -111,6 +113,31 @@ Debug,
 )]
 pub struct ValidityTs(pub Reverse<i64>);
 
+impl ValidityTs {
+    /// The latest possible timestamp, the same as `'END'` in queries.
+    /// Being latest, it sorts before every other timestamp.
+    pub const LATEST: Self = ValidityTs(Reverse(i64::MAX));
+    /// The earliest possible timestamp. It sorts after every other timestamp.
+    pub const EARLIEST: Self = ValidityTs(Reverse(i64::MIN));
+
+    /// The current time
+    pub fn now() -> Self {
+        current_validity()
+    }
+    /// Construct from microseconds since the UNIX epoch
+    pub fn from_micros(micros: i64) -> Self {
+        ValidityTs(Reverse(micros))
+    }
+    /// Microseconds since the UNIX epoch
+    pub fn as_micros(&self) -> i64 {
+        self.0 .0
+    }
+    /// Whether this timestamp is chronologically later than `other`
+    pub fn is_after(&self, other: &Self) -> bool {
+        self.as_micros() > other.as_micros()
+    }
+}
+
 /// Validity for time travel
 #[derive(
 Copy,
@@ -48,15 +48,17 @@ use miette::{
 };
 use serde_json::json;
 
+pub use data::relation::{ColumnSpec, RelationDef};
 pub use data::value::{DataValue, Num, RegexWrapper, UuidWrapper, Validity, ValidityTs};
 pub use fixed_rule::{FixedRule, FixedRuleInputRelation, FixedRulePayload};
 pub use runtime::db::Db;
 pub use runtime::db::NamedRows;
+pub use runtime::db::NodeTrace;
 pub use runtime::relation::decode_tuple_from_kv;
 pub use runtime::temp_store::RegularTempStore;
 pub use storage::mem::{new_cozo_mem, MemStorage};
 #[cfg(feature = "storage-rocksdb")]
-pub use storage::rocks::{new_cozo_rocksdb, RocksDbStorage};
+pub use storage::rocks::{new_cozo_rocksdb, new_cozo_rocksdb_temp, RocksDbStorage};
 #[cfg(feature = "storage-sled")]
 pub use storage::sled::{new_cozo_sled, SledStorage};
 #[cfg(feature = "storage-sqlite")]
@@ -74,6 +76,7 @@ pub use crate::parse::SourceSpan;
 pub use crate::runtime::callback::CallbackOp;
 pub use crate::runtime::db::evaluate_expressions;
 pub use crate::runtime::db::get_variables;
+pub use crate::runtime::db::ImportMode;
 pub use crate::runtime::db::Poison;
 pub use crate::runtime::db::ScriptMutability;
 pub use crate::runtime::db::TransactionPayload;
@@ -189,6 +192,97 @@ impl DbInstance {
             DbInstance::TiKv(db) => db.run_script(payload, params, mutability),
         }
     }
+    /// Dispatcher method. See [crate::Db::run_script_at].
+    pub fn run_script_at(
+        &self,
+        payload: &str,
+        params: BTreeMap<String, DataValue>,
+        valid_at: ValidityTs,
+        mutability: ScriptMutability,
+    ) -> Result<NamedRows> {
+        match self {
+            DbInstance::Mem(db) => db.run_script_at(payload, params, valid_at, mutability),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(db) => db.run_script_at(payload, params, valid_at, mutability),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(db) => db.run_script_at(payload, params, valid_at, mutability),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(db) => db.run_script_at(payload, params, valid_at, mutability),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(db) => db.run_script_at(payload, params, valid_at, mutability),
+        }
+    }
+    /// Dispatcher method. See [crate::Db::run_scripts].
+    pub fn run_scripts(
+        &self,
+        payloads: &[&str],
+        params: BTreeMap<String, DataValue>,
+    ) -> Result<Vec<NamedRows>> {
+        match self {
+            DbInstance::Mem(db) => db.run_scripts(payloads, params),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(db) => db.run_scripts(payloads, params),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(db) => db.run_scripts(payloads, params),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(db) => db.run_scripts(payloads, params),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(db) => db.run_scripts(payloads, params),
+        }
+    }
+    /// Dispatcher method. See [crate::Db::count_query].
+    pub fn count_query(&self, payload: &str, params: BTreeMap<String, DataValue>) -> Result<usize> {
+        match self {
+            DbInstance::Mem(db) => db.count_query(payload, params),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(db) => db.count_query(payload, params),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(db) => db.count_query(payload, params),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(db) => db.count_query(payload, params),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(db) => db.count_query(payload, params),
+        }
+    }
+    /// Dispatcher method. See [crate::Db::run_script_explain_analyze].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn run_script_explain_analyze(
+        &self,
+        payload: &str,
+        params: BTreeMap<String, DataValue>,
+    ) -> Result<NamedRows> {
+        match self {
+            DbInstance::Mem(db) => db.run_script_explain_analyze(payload, params),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(db) => db.run_script_explain_analyze(payload, params),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(db) => db.run_script_explain_analyze(payload, params),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(db) => db.run_script_explain_analyze(payload, params),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(db) => db.run_script_explain_analyze(payload, params),
+        }
+    }
+    /// Dispatcher method. See [crate::Db::trace_query].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn trace_query(
+        &self,
+        payload: &str,
+        params: BTreeMap<String, DataValue>,
+        max_samples: usize,
+    ) -> Result<(NamedRows, Vec<NodeTrace>)> {
+        match self {
+            DbInstance::Mem(db) => db.trace_query(payload, params, max_samples),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(db) => db.trace_query(payload, params, max_samples),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(db) => db.trace_query(payload, params, max_samples),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(db) => db.trace_query(payload, params, max_samples),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(db) => db.trace_query(payload, params, max_samples),
+        }
+    }
     /// `run_script` with mutable script and no parameters
     pub fn run_default(&self, payload: &str) -> Result<NamedRows> {
         self.run_script(payload, BTreeMap::new(), ScriptMutability::Mutable)
@@ -248,6 +342,24 @@ impl DbInstance {
         )
             .to_string()
     }
+    /// Dispatcher method. See [crate::Db::warmup].
+    pub fn warmup<I, T>(&self, relations: I) -> Result<usize>
+    where
+        T: AsRef<str>,
+        I: Iterator<Item = T>,
+    {
+        match self {
+            DbInstance::Mem(db) => db.warmup(relations),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(db) => db.warmup(relations),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(db) => db.warmup(relations),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(db) => db.warmup(relations),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(db) => db.warmup(relations),
+        }
+    }
     /// Dispatcher method. See [crate::Db::export_relations].
     pub fn export_relations<I, T>(&self, relations: I) -> Result<BTreeMap<String, NamedRows>>
         where
@@ -266,6 +378,52 @@ impl DbInstance {
             DbInstance::TiKv(db) => db.export_relations(relations),
         }
     }
+    /// Dispatcher method. See [crate::Db::replicate_to].
+    pub fn replicate_to<I, T>(
+        &self,
+        target: &DbInstance,
+        relations: I,
+        since: Option<ValidityTs>,
+    ) -> Result<usize>
+    where
+        T: AsRef<str>,
+        I: Iterator<Item = T>,
+    {
+        match target {
+            DbInstance::Mem(target) => self.replicate_to_db(target, relations, since),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(target) => self.replicate_to_db(target, relations, since),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(target) => self.replicate_to_db(target, relations, since),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(target) => self.replicate_to_db(target, relations, since),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(target) => self.replicate_to_db(target, relations, since),
+        }
+    }
+    fn replicate_to_db<'t, S, I, T>(
+        &self,
+        target: &'t Db<S>,
+        relations: I,
+        since: Option<ValidityTs>,
+    ) -> Result<usize>
+    where
+        S: Storage<'t>,
+        T: AsRef<str>,
+        I: Iterator<Item = T>,
+    {
+        match self {
+            DbInstance::Mem(db) => db.replicate_to(target, relations, since),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(db) => db.replicate_to(target, relations, since),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(db) => db.replicate_to(target, relations, since),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(db) => db.replicate_to(target, relations, since),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(db) => db.replicate_to(target, relations, since),
+        }
+    }
     /// Export relations to JSON-encoded string.
     /// See [crate::Db::export_relations]
     pub fn export_relations_str(&self, data: &str) -> String {
@@ -292,6 +450,20 @@ impl DbInstance {
             .map(|(k, v)| (k, v.into_json()))
             .collect())
     }
+    /// Dispatcher method. See [crate::Db::create_relation].
+    pub fn create_relation(&self, def: &RelationDef) -> Result<()> {
+        match self {
+            DbInstance::Mem(db) => db.create_relation(def),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(db) => db.create_relation(def),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(db) => db.create_relation(def),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(db) => db.create_relation(def),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(db) => db.create_relation(def),
+        }
+    }
     /// Dispatcher method. See [crate::Db::import_relations].
     pub fn import_relations(&self, data: BTreeMap<String, NamedRows>) -> Result<()> {
         match self {
@@ -306,6 +478,24 @@ impl DbInstance {
             DbInstance::TiKv(db) => db.import_relations(data),
         }
     }
+    /// Dispatcher method. See [crate::Db::import_relations_with_mode].
+    pub fn import_relations_with_mode(
+        &self,
+        data: BTreeMap<String, NamedRows>,
+        mode: ImportMode,
+    ) -> Result<()> {
+        match self {
+            DbInstance::Mem(db) => db.import_relations_with_mode(data, mode),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(db) => db.import_relations_with_mode(data, mode),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(db) => db.import_relations_with_mode(data, mode),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(db) => db.import_relations_with_mode(data, mode),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(db) => db.import_relations_with_mode(data, mode),
+        }
+    }
     /// Import a relation, the data is given as a JSON string, and the returned result is converted into a string.
     /// See [crate::Db::import_relations].
     pub fn import_relations_str(&self, data: &str) -> String {
@@ -333,6 +523,132 @@ impl DbInstance {
             .collect::<Result<_>>()?;
         self.import_relations(mapping)
     }
+    /// Dispatcher method. See [crate::Db::get_value].
+    pub fn get_value(
+        &self,
+        relation: &str,
+        key: &[DataValue],
+        column: &str,
+    ) -> Result<Option<DataValue>> {
+        match self {
+            DbInstance::Mem(db) => db.get_value(relation, key, column),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(db) => db.get_value(relation, key, column),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(db) => db.get_value(relation, key, column),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(db) => db.get_value(relation, key, column),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(db) => db.get_value(relation, key, column),
+        }
+    }
+    /// Dispatcher method. See [crate::Db::get_rows].
+    pub fn get_rows(
+        &self,
+        relation: &str,
+        keys: &[Vec<DataValue>],
+    ) -> Result<Vec<Option<Vec<DataValue>>>> {
+        match self {
+            DbInstance::Mem(db) => db.get_rows(relation, keys),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(db) => db.get_rows(relation, keys),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(db) => db.get_rows(relation, keys),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(db) => db.get_rows(relation, keys),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(db) => db.get_rows(relation, keys),
+        }
+    }
+    /// Dispatcher method. See [crate::Db::version_count].
+    pub fn version_count(&self, relation: &str, key: &[DataValue]) -> Result<usize> {
+        match self {
+            DbInstance::Mem(db) => db.version_count(relation, key),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(db) => db.version_count(relation, key),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(db) => db.version_count(relation, key),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(db) => db.version_count(relation, key),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(db) => db.version_count(relation, key),
+        }
+    }
+    /// Dispatcher method. See [crate::Db::column_value_histogram].
+    pub fn column_value_histogram(
+        &self,
+        relation: &str,
+        column: &str,
+        top_n: usize,
+    ) -> Result<NamedRows> {
+        match self {
+            DbInstance::Mem(db) => db.column_value_histogram(relation, column, top_n),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(db) => db.column_value_histogram(relation, column, top_n),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(db) => db.column_value_histogram(relation, column, top_n),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(db) => db.column_value_histogram(relation, column, top_n),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(db) => db.column_value_histogram(relation, column, top_n),
+        }
+    }
+    /// Dispatcher method. See [crate::Db::expire].
+    pub fn expire(&self, relation: &str, older_than: ValidityTs) -> Result<usize> {
+        match self {
+            DbInstance::Mem(db) => db.expire(relation, older_than),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(db) => db.expire(relation, older_than),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(db) => db.expire(relation, older_than),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(db) => db.expire(relation, older_than),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(db) => db.expire(relation, older_than),
+        }
+    }
+    /// Dispatcher method. See [crate::Db::gc].
+    pub fn gc(&self, before: ValidityTs) -> Result<usize> {
+        match self {
+            DbInstance::Mem(db) => db.gc(before),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(db) => db.gc(before),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(db) => db.gc(before),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(db) => db.gc(before),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(db) => db.gc(before),
+        }
+    }
+    /// Dispatcher method. See [crate::Db::flush].
+    pub fn flush(&self) -> Result<()> {
+        match self {
+            DbInstance::Mem(db) => db.flush(),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(db) => db.flush(),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(db) => db.flush(),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(db) => db.flush(),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(db) => db.flush(),
+        }
+    }
+    /// Dispatcher method. See [crate::Db::close].
+    pub fn close(self) -> Result<()> {
+        match self {
+            DbInstance::Mem(db) => db.close(),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(db) => db.close(),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(db) => db.close(),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(db) => db.close(),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(db) => db.close(),
+        }
+    }
     /// Dispatcher method. See [crate::Db::backup_db].
     pub fn backup_db(&self, out_file: impl AsRef<Path>) -> Result<()> {
         match self {
@@ -449,6 +765,68 @@ impl DbInstance {
             DbInstance::TiKv(db) => db.unregister_callback(id),
         }
     }
+    /// Dispatcher method. See [crate::Db::set_clock].
+    pub fn set_clock<F>(&self, clock: F)
+    where
+        F: Fn() -> ValidityTs + Send + Sync + 'static,
+    {
+        match self {
+            DbInstance::Mem(db) => db.set_clock(clock),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(db) => db.set_clock(clock),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(db) => db.set_clock(clock),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(db) => db.set_clock(clock),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(db) => db.set_clock(clock),
+        }
+    }
+    /// Dispatcher method. See [crate::Db::reset_clock].
+    pub fn reset_clock(&self) {
+        match self {
+            DbInstance::Mem(db) => db.reset_clock(),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(db) => db.reset_clock(),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(db) => db.reset_clock(),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(db) => db.reset_clock(),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(db) => db.reset_clock(),
+        }
+    }
+    /// Dispatcher method. See [crate::Db::set_yield_hook].
+    pub fn set_yield_hook<F>(&self, every: usize, hook: F)
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        match self {
+            DbInstance::Mem(db) => db.set_yield_hook(every, hook),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(db) => db.set_yield_hook(every, hook),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(db) => db.set_yield_hook(every, hook),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(db) => db.set_yield_hook(every, hook),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(db) => db.set_yield_hook(every, hook),
+        }
+    }
+    /// Dispatcher method. See [crate::Db::clear_yield_hook].
+    pub fn clear_yield_hook(&self) {
+        match self {
+            DbInstance::Mem(db) => db.clear_yield_hook(),
+            #[cfg(feature = "storage-sqlite")]
+            DbInstance::Sqlite(db) => db.clear_yield_hook(),
+            #[cfg(feature = "storage-rocksdb")]
+            DbInstance::RocksDb(db) => db.clear_yield_hook(),
+            #[cfg(feature = "storage-sled")]
+            DbInstance::Sled(db) => db.clear_yield_hook(),
+            #[cfg(feature = "storage-tikv")]
+            DbInstance::TiKv(db) => db.clear_yield_hook(),
+        }
+    }
     /// Dispatcher method. See [crate::Db::register_fixed_rule].
     pub fn register_fixed_rule<R>(&self, name: String, rule_impl: R) -> Result<()>
         where
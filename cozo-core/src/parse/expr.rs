@@ -448,11 +448,48 @@ struct InvalidUtf8Error(u32, #[label] SourceSpan);
 #[diagnostic(code(parser::invalid_escape_seq))]
 struct InvalidEscapeSeqError(String, #[label] SourceSpan);
 
+/// Decodes a `\uXXXX` escape, combining UTF-16 surrogate pairs such as
+/// `\ud83d\ude00` into a single character.
+fn push_unicode_escape(
+    ret: &mut SmartString<LazyCompact>,
+    high_surrogate: &mut Option<(u32, SourceSpan)>,
+    s: &str,
+    span: SourceSpan,
+) -> Result<()> {
+    let code = parse_int(s, 16) as u32;
+    let code = match high_surrogate.take() {
+        Some((high, _)) if (0xDC00..0xE000).contains(&code) => {
+            0x10000 + ((high - 0xD800) << 10) + (code - 0xDC00)
+        }
+        Some((high, high_span)) => bail!(InvalidUtf8Error(high, high_span)),
+        None if (0xD800..0xDC00).contains(&code) => {
+            *high_surrogate = Some((code, span));
+            return Ok(());
+        }
+        None => code,
+    };
+    let ch = char::from_u32(code).ok_or(InvalidUtf8Error(code, span))?;
+    ret.push(ch);
+    Ok(())
+}
+
+fn ensure_no_dangling_surrogate(
+    high_surrogate: Option<(u32, SourceSpan)>,
+    next: &str,
+) -> Result<()> {
+    match high_surrogate {
+        Some((high, span)) if !next.starts_with(r"\u") => bail!(InvalidUtf8Error(high, span)),
+        _ => Ok(()),
+    }
+}
+
 fn parse_quoted_string(pair: Pair<'_>) -> Result<SmartString<LazyCompact>> {
     let pairs = pair.into_inner().next().unwrap().into_inner();
     let mut ret = SmartString::new();
+    let mut high_surrogate = None;
     for pair in pairs {
         let s = pair.as_str();
+        ensure_no_dangling_surrogate(high_surrogate, s)?;
         match s {
             r#"\""# => ret.push('"'),
             r"\\" => ret.push('\\'),
@@ -463,10 +500,7 @@ fn parse_quoted_string(pair: Pair<'_>) -> Result<SmartString<LazyCompact>> {
             r"\r" => ret.push('\r'),
             r"\t" => ret.push('\t'),
             s if s.starts_with(r"\u") => {
-                let code = parse_int(s, 16) as u32;
-                let ch = char::from_u32(code)
-                    .ok_or_else(|| InvalidUtf8Error(code, pair.extract_span()))?;
-                ret.push(ch);
+                push_unicode_escape(&mut ret, &mut high_surrogate, s, pair.extract_span())?
             }
             s if s.starts_with('\\') => {
                 bail!(InvalidEscapeSeqError(s.to_string(), pair.extract_span()))
@@ -474,14 +508,17 @@ fn parse_quoted_string(pair: Pair<'_>) -> Result<SmartString<LazyCompact>> {
             s => ret.push_str(s),
         }
     }
+    ensure_no_dangling_surrogate(high_surrogate, "")?;
     Ok(ret)
 }
 
 fn parse_s_quoted_string(pair: Pair<'_>) -> Result<SmartString<LazyCompact>> {
     let pairs = pair.into_inner().next().unwrap().into_inner();
     let mut ret = SmartString::new();
+    let mut high_surrogate = None;
     for pair in pairs {
         let s = pair.as_str();
+        ensure_no_dangling_surrogate(high_surrogate, s)?;
         match s {
             r#"\'"# => ret.push('\''),
             r"\\" => ret.push('\\'),
@@ -492,10 +529,7 @@ fn parse_s_quoted_string(pair: Pair<'_>) -> Result<SmartString<LazyCompact>> {
             r"\r" => ret.push('\r'),
             r"\t" => ret.push('\t'),
             s if s.starts_with(r"\u") => {
-                let code = parse_int(s, 16) as u32;
-                let ch = char::from_u32(code)
-                    .ok_or_else(|| InvalidUtf8Error(code, pair.extract_span()))?;
-                ret.push(ch);
+                push_unicode_escape(&mut ret, &mut high_surrogate, s, pair.extract_span())?
             }
             s if s.starts_with('\\') => {
                 bail!(InvalidEscapeSeqError(s.to_string(), pair.extract_span()))
@@ -503,6 +537,7 @@ fn parse_s_quoted_string(pair: Pair<'_>) -> Result<SmartString<LazyCompact>> {
             s => ret.push_str(s),
         }
     }
+    ensure_no_dangling_surrogate(high_surrogate, "")?;
     Ok(ret)
 }
 
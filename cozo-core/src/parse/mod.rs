@@ -254,6 +254,14 @@ pub(crate) fn parse_type(src: &str) -> Result<NullableColType> {
     parse_nullable_type(parsed.into_inner().next().unwrap())
 }
 
+/// Whether `src` is, in its entirety, a match for the identifier `rule`
+pub(crate) fn is_identifier(rule: Rule, src: &str) -> bool {
+    CozoScriptParser::parse(rule, src)
+        .ok()
+        .and_then(|mut parsed| parsed.next())
+        .is_some_and(|parsed| parsed.as_str() == src)
+}
+
 pub(crate) fn parse_expressions(
     src: &str,
     param_pool: &BTreeMap<String, DataValue>,
@@ -22,9 +22,9 @@ use thiserror::Error;
 
 use crate::data::aggr::{parse_aggr, Aggregation};
 use crate::data::expr::Expr;
-use crate::data::functions::{str2vld, MAX_VALIDITY_TS};
+use crate::data::functions::{str2vld, MAX_VALIDITY_TS, OP_IS_IN, OP_NEGATE};
 use crate::data::program::{
-    FixedRuleApply, FixedRuleArg, InputAtom, InputInlineRule, InputInlineRulesOrFixed,
+    Collation, FixedRuleApply, FixedRuleArg, InputAtom, InputInlineRule, InputInlineRulesOrFixed,
     InputNamedFieldRelationApplyAtom, InputProgram, InputRelationApplyAtom, InputRuleApplyAtom,
     QueryAssertion, QueryOutOptions, RelationOp, ReturnMutation, SearchInput, SortDir, Unification,
 };
@@ -72,6 +72,12 @@ struct DuplicateQueryAssertion(#[label] SourceSpan);
 #[diagnostic(code(parser::multiple_yields))]
 struct DuplicateYield(#[label] SourceSpan);
 
+#[derive(Debug, Error, Diagnostic)]
+#[error("The entry rule cannot be defined with ':with'")]
+#[diagnostic(code(parser::with_entry_rule))]
+#[diagnostic(help("':with' introduces helper rules local to the script; define '?' without it"))]
+struct WithEntryRuleError(#[label] SourceSpan);
+
 impl Error for MultipleRuleDefinitionError {}
 
 impl Display for MultipleRuleDefinitionError {
@@ -112,11 +118,28 @@ pub(crate) fn parse_query(
     let mut progs: BTreeMap<Symbol, InputInlineRulesOrFixed> = Default::default();
     let mut out_opts: QueryOutOptions = Default::default();
     let mut disable_magic_rewrite = false;
+    let mut lenient = false;
 
     let mut stored_relation = None;
     let mut returning_mutation = ReturnMutation::NotReturning;
 
     for pair in src {
+        let pair = if pair.as_rule() == Rule::with_rule {
+            let span = pair.extract_span();
+            let inner = pair.into_inner().next().unwrap();
+            let head_name = inner
+                .clone()
+                .into_inner()
+                .next()
+                .unwrap()
+                .into_inner()
+                .next()
+                .unwrap();
+            ensure!(head_name.as_str() != PROG_ENTRY, WithEntryRuleError(span));
+            inner
+        } else {
+            pair
+        };
         match pair.as_rule() {
             Rule::rule => {
                 let (name, rule) = parse_rule(pair, param_pool, cur_vld)?;
@@ -298,6 +321,26 @@ pub(crate) fn parse_query(
                     .ok_or(OptionNotNonNegIntError("limit", span))?;
                 out_opts.limit = Some(limit as usize);
             }
+            Rule::max_group_values_option => {
+                let pair = pair.into_inner().next().unwrap();
+                let span = pair.extract_span();
+                let cap = build_expr(pair, param_pool)?
+                    .eval_to_const()
+                    .map_err(|err| OptionNotConstantError("max_group_values", span, [err]))?
+                    .get_non_neg_int()
+                    .ok_or(OptionNotNonNegIntError("max_group_values", span))?;
+                out_opts.max_group_values = Some(cap as usize);
+            }
+            Rule::max_aggr_values_option => {
+                let pair = pair.into_inner().next().unwrap();
+                let span = pair.extract_span();
+                let cap = build_expr(pair, param_pool)?
+                    .eval_to_const()
+                    .map_err(|err| OptionNotConstantError("max_aggr_values", span, [err]))?
+                    .get_non_neg_int()
+                    .ok_or(OptionNotNonNegIntError("max_aggr_values", span))?;
+                out_opts.max_aggr_values = Some(cap as usize);
+            }
             Rule::offset_option => {
                 let pair = pair.into_inner().next().unwrap();
                 let span = pair.extract_span();
@@ -312,6 +355,7 @@ pub(crate) fn parse_query(
                 for part in pair.into_inner() {
                     let mut var = "";
                     let mut dir = SortDir::Asc;
+                    let mut collation = Collation::Binary;
                     let mut span = part.extract_span();
                     for a in part.into_inner() {
                         match a.as_rule() {
@@ -321,10 +365,28 @@ pub(crate) fn parse_query(
                             }
                             Rule::sort_asc => dir = SortDir::Asc,
                             Rule::sort_desc => dir = SortDir::Dsc,
+                            Rule::sort_collation => {
+                                let name = a.into_inner().next().unwrap();
+                                collation = match name.as_str() {
+                                    "binary" => Collation::Binary,
+                                    "unicode" => Collation::Unicode,
+                                    s => {
+                                        #[derive(Debug, Error, Diagnostic)]
+                                        #[error("Unknown collation '{0}'")]
+                                        #[diagnostic(code(parser::unknown_collation))]
+                                        #[diagnostic(help("Use 'binary' or 'unicode'"))]
+                                        struct UnknownCollation(String, #[label] SourceSpan);
+
+                                        bail!(UnknownCollation(s.to_string(), name.extract_span()))
+                                    }
+                                }
+                            }
                             _ => unreachable!(),
                         }
                     }
-                    out_opts.sorters.push((Symbol::new(var, span), dir));
+                    out_opts
+                        .sorters
+                        .push((Symbol::new(var, span), dir, collation));
                 }
             }
             Rule::returning_option => {
@@ -396,6 +458,9 @@ pub(crate) fn parse_query(
                     .ok_or(OptionNotBoolError("disable_magic_rewrite", span))?;
                 disable_magic_rewrite = val;
             }
+            Rule::lenient_option => {
+                lenient = true;
+            }
             Rule::EOI => break,
             r => unreachable!("{:?}", r),
         }
@@ -405,6 +470,7 @@ pub(crate) fn parse_query(
         prog: progs,
         out_opts,
         disable_magic_rewrite,
+        lenient,
     };
 
     if prog.prog.is_empty() {
@@ -444,6 +510,7 @@ pub(crate) fn parse_query(
                             nullable: true,
                         },
                         default_gen: None,
+                        description: Default::default(),
                     })
                     .collect(),
                 non_keys: vec![],
@@ -477,7 +544,7 @@ pub(crate) fn parse_query(
 
         let head_args = prog.get_entry_out_head()?;
 
-        for (sorter, _) in &prog.out_opts.sorters {
+        for (sorter, _, _) in &prog.out_opts.sorters {
             ensure!(
                 head_args.contains(sorter),
                 SortKeyNotFound(sorter.to_string(), sorter.span)
@@ -521,6 +588,7 @@ pub(crate) fn parse_query(
                         nullable: true,
                     },
                     default_gen: None,
+                    description: Default::default(),
                 })
                 .collect();
         } else {
@@ -621,6 +689,16 @@ fn parse_atom(
                 span,
             }
         }
+        Rule::optional => {
+            let span = src.extract_span();
+            let mut src = src.into_inner();
+            src.next().unwrap();
+            let inner = parse_atom(src.next().unwrap(), param_pool, cur_vld, ignored_counter)?;
+            InputAtom::Optional {
+                inner: inner.into(),
+                span,
+            }
+        }
         Rule::expr => {
             let expr = build_expr(src, param_pool)?;
             InputAtom::Predicate { inner: expr }
@@ -664,6 +742,30 @@ fn parse_atom(
                 },
             }
         }
+        Rule::not_in => {
+            let span = src.extract_span();
+            let mut src = src.into_inner();
+            let var = src.next().unwrap();
+            let binding = Expr::Binding {
+                var: Symbol::new(var.as_str(), var.extract_span()),
+                tuple_pos: None,
+            };
+            src.next().unwrap();
+            src.next().unwrap();
+            let expr = build_expr(src.next().unwrap(), param_pool)?;
+            InputAtom::Predicate {
+                inner: Expr::Apply {
+                    op: &OP_NEGATE,
+                    args: [Expr::Apply {
+                        op: &OP_IS_IN,
+                        args: [binding, expr].into(),
+                        span,
+                    }]
+                    .into(),
+                    span,
+                },
+            }
+        }
         Rule::rule_apply => {
             let span = src.extract_span();
             let mut src = src.into_inner();
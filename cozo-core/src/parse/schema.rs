@@ -16,7 +16,7 @@ use thiserror::Error;
 use crate::data::relation::{VecElementType, ColType, ColumnDef, NullableColType, StoredRelationMetadata};
 use crate::data::symb::Symbol;
 use crate::data::value::DataValue;
-use crate::parse::expr::{build_expr};
+use crate::parse::expr::{build_expr, parse_string};
 use crate::parse::{ExtractSpan, Pair, Rule, SourceSpan};
 
 pub(crate) fn parse_schema(
@@ -73,11 +73,15 @@ fn parse_col(pair: Pair<'_>) -> Result<(ColumnDef, Symbol)> {
         nullable: true,
     };
     let mut default_gen = None;
+    let mut description = SmartString::default();
     let mut binding_candidate = None;
     for nxt in src {
         match nxt.as_rule() {
             Rule::col_type => typing = parse_nullable_type(nxt)?,
             Rule::expr => default_gen = Some(build_expr(nxt, &Default::default())?),
+            Rule::quoted_string | Rule::s_quoted_string | Rule::raw_string => {
+                description = parse_string(nxt)?
+            }
             Rule::out_arg => {
                 binding_candidate = Some(Symbol::new(nxt.as_str(), nxt.extract_span()))
             }
@@ -91,6 +95,7 @@ fn parse_col(pair: Pair<'_>) -> Result<(ColumnDef, Symbol)> {
             name,
             typing,
             default_gen,
+            description,
         },
         binding,
     ))
@@ -315,7 +315,7 @@ impl<'a> SessionTx<'a> {
 
         for (rule_n, rule) in ruleset.iter().enumerate() {
             debug!("initial calculation for rule {:?}.{}", rule_symb, rule_n);
-            for item_res in rule.relation.iter(self, None, stores)? {
+            for item_res in rule.relation.iter_rule_body(self, None, stores)? {
                 let item = item_res?;
                 trace!("item for {:?}.{}: {:?} at {}", rule_symb, rule_n, item, 0);
                 if should_check_limit {
@@ -354,7 +354,7 @@ impl<'a> SessionTx<'a> {
             for (aggr, args) in aggr.iter_mut().flatten() {
                 aggr.meet_init(args)?;
             }
-            for item_res in rule.relation.iter(self, None, stores)? {
+            for item_res in rule.relation.iter_rule_body(self, None, stores)? {
                 let item = item_res?;
                 trace!("item for {:?}.{}: {:?} at {}", rule_symb, rule_n, item, 0);
                 out_store.meet_put(item)?;
@@ -389,6 +389,7 @@ impl<'a> SessionTx<'a> {
         let mut out_store = RegularTempStore::default();
         let should_check_limit = limiter.total.is_some() && rule_symb.is_prog_entry();
         let mut aggr_work: BTreeMap<Vec<DataValue>, Vec<Aggregation>> = BTreeMap::new();
+        let mut total_buffered = 0usize;
 
         for (rule_n, rule) in ruleset.iter().enumerate() {
             debug!(
@@ -414,7 +415,7 @@ impl<'a> SessionTx<'a> {
                 .filter_map(|(i, a)| a.as_ref().map(|aggr| (i, aggr.clone())))
                 .collect_vec();
 
-            for item_res in rule.relation.iter(self, None, stores)? {
+            for item_res in rule.relation.iter_rule_body(self, None, stores)? {
                 let item = item_res?;
                 trace!("item for {:?}.{}: {:?} at {}", rule_symb, rule_n, item, 0);
 
@@ -424,11 +425,16 @@ impl<'a> SessionTx<'a> {
                     Entry::Occupied(mut ent) => {
                         let aggr_ops = ent.get_mut();
                         for (aggr_idx, (tuple_idx, _)) in val_indices_and_aggrs.iter().enumerate() {
-                            aggr_ops[aggr_idx]
-                                .normal_op
-                                .as_mut()
-                                .unwrap()
-                                .set(&item[*tuple_idx])?;
+                            let aggr = &mut aggr_ops[aggr_idx];
+                            let op = aggr.normal_op.as_mut().unwrap();
+                            let before = op.buffered();
+                            op.set(&item[*tuple_idx])?;
+                            self.aggr_limits.track(
+                                aggr.name,
+                                before,
+                                op.buffered(),
+                                &mut total_buffered,
+                            )?;
                         }
                     }
                     Entry::Vacant(ent) => {
@@ -436,7 +442,14 @@ impl<'a> SessionTx<'a> {
                         for (i, (aggr, params)) in &val_indices_and_aggrs {
                             let mut cur_aggr = aggr.clone();
                             cur_aggr.normal_init(params)?;
-                            cur_aggr.normal_op.as_mut().unwrap().set(&item[*i])?;
+                            let op = cur_aggr.normal_op.as_mut().unwrap();
+                            op.set(&item[*i])?;
+                            self.aggr_limits.track(
+                                cur_aggr.name,
+                                0,
+                                op.buffered(),
+                                &mut total_buffered,
+                            )?;
                             aggr_ops.push(cur_aggr)
                         }
                         ent.insert(aggr_ops);
@@ -536,7 +549,7 @@ impl<'a> SessionTx<'a> {
 
             if need_complete_run {
                 debug!("complete rule for rule {:?}.{}", rule_symb, rule_n);
-                for item_res in rule.relation.iter(self, None, stores)? {
+                for item_res in rule.relation.iter_rule_body(self, None, stores)? {
                     let item = item_res?;
                     // improvement: the clauses can actually be evaluated in parallel
                     if prev_store.exists(&item) {
@@ -576,7 +589,10 @@ impl<'a> SessionTx<'a> {
                         "with delta {:?} for rule {:?}.{}",
                         delta_key, rule_symb, rule_n
                     );
-                    for item_res in rule.relation.iter(self, Some(delta_key), stores)? {
+                    for item_res in rule
+                        .relation
+                        .iter_rule_body(self, Some(delta_key), stores)?
+                    {
                         let item = item_res?;
                         // improvement: the clauses can actually be evaluated in parallel
                         if prev_store.exists(&item) {
@@ -645,7 +661,7 @@ impl<'a> SessionTx<'a> {
 
             if need_complete_run {
                 debug!("complete run for rule {:?}.{}", rule_symb, rule_n);
-                for item_res in rule.relation.iter(self, None, stores)? {
+                for item_res in rule.relation.iter_rule_body(self, None, stores)? {
                     out_store.meet_put(item_res?)?;
                 }
                 poison.check()?;
@@ -658,7 +674,10 @@ impl<'a> SessionTx<'a> {
                         "with delta {:?} for rule {:?}.{}",
                         delta_key, rule_symb, rule_n
                     );
-                    for item_res in rule.relation.iter(self, Some(delta_key), stores)? {
+                    for item_res in rule
+                        .relation
+                        .iter_rule_body(self, Some(delta_key), stores)?
+                    {
                         out_store.meet_put(item_res?)?;
                     }
                     poison.check()?;
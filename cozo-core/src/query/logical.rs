@@ -6,7 +6,7 @@
  * You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 
 use itertools::Itertools;
 use miette::{bail, ensure, Diagnostic, Result};
@@ -17,6 +17,8 @@ use crate::data::program::{
     InputAtom, InputNamedFieldRelationApplyAtom, InputRelationApplyAtom, InputRuleApplyAtom,
     NormalFormAtom, NormalFormRelationApplyAtom, NormalFormRuleApplyAtom, TempSymbGen, Unification,
 };
+use crate::data::symb::Symbol;
+use crate::data::value::DataValue;
 use crate::parse::SourceSpan;
 use crate::query::reorder::UnsafeNegation;
 use crate::runtime::transact::SessionTx;
@@ -57,7 +59,300 @@ impl Disjunction {
 #[derive(Debug)]
 pub(crate) struct Conjunction(pub(crate) Vec<NormalFormAtom>);
 
+#[derive(Debug, Error, Diagnostic)]
+#[error("'optional' can only be applied at the top level of a rule body")]
+#[diagnostic(code(parser::nested_optional))]
+pub(crate) struct NestedOptional(#[label] pub(crate) SourceSpan);
+
 impl InputAtom {
+    /// Expands each top-level `optional A` of a rule body into the left outer join
+    /// `(A) or (not A, v = null, ...)`, where the `v`s are the variables occurring only in `A`.
+    /// For a stored relation, a `v` bound to a column with a declared default gets the
+    /// default instead of null.
+    pub(crate) fn expand_optional_atoms(
+        body: Vec<InputAtom>,
+        tx: &SessionTx<'_>,
+    ) -> Result<Vec<InputAtom>> {
+        if !body.iter().any(|a| matches!(a, InputAtom::Optional { .. })) {
+            return Ok(body);
+        }
+        let mut ret = Vec::with_capacity(body.len());
+        for (i, atom) in body.iter().enumerate() {
+            let (inner, span) = match atom {
+                InputAtom::Optional { inner, span } => (inner, *span),
+                a => {
+                    ret.push(a.clone());
+                    continue;
+                }
+            };
+            let mut outside = BTreeSet::new();
+            for (j, other) in body.iter().enumerate() {
+                if i != j {
+                    other.collect_bindings(&mut outside)?;
+                }
+            }
+            let mut own = BTreeSet::new();
+            inner.collect_bindings(&mut own)?;
+            let optional_only: BTreeSet<_> = own
+                .into_iter()
+                .filter(|v| !outside.contains(v) && !v.is_ignored_symbol())
+                .collect();
+
+            let mut defaults = BTreeMap::new();
+            let mut add_default = |arg: Option<&Expr>, default_gen: &Option<Expr>| {
+                if let (Some(Expr::Binding { var, .. }), Some(default)) = (arg, default_gen) {
+                    defaults.insert(var.clone(), default.clone());
+                }
+            };
+            match &**inner {
+                InputAtom::NamedFieldRelation { inner } => {
+                    let stored = tx.get_relation(&inner.name, false)?;
+                    for col in stored
+                        .metadata
+                        .keys
+                        .iter()
+                        .chain(stored.metadata.non_keys.iter())
+                    {
+                        add_default(inner.args.get(&col.name), &col.default_gen);
+                    }
+                }
+                InputAtom::Relation { inner } => {
+                    let stored = tx.get_relation(&inner.name, false)?;
+                    for (col, arg) in stored
+                        .metadata
+                        .keys
+                        .iter()
+                        .chain(stored.metadata.non_keys.iter())
+                        .zip(inner.args.iter())
+                    {
+                        add_default(Some(arg), &col.default_gen);
+                    }
+                }
+                _ => {}
+            }
+
+            let mut missing = vec![InputAtom::Negation {
+                inner: Box::new(inner.ignoring_bindings(&optional_only)),
+                span,
+            }];
+            for var in optional_only {
+                let expr = defaults.remove(&var).unwrap_or(Expr::Const {
+                    val: DataValue::Null,
+                    span,
+                });
+                missing.push(InputAtom::Unification {
+                    inner: Unification {
+                        binding: var,
+                        expr,
+                        one_many_unif: false,
+                        span,
+                    },
+                })
+            }
+            ret.push(InputAtom::Disjunction {
+                inner: vec![
+                    (**inner).clone(),
+                    InputAtom::Conjunction {
+                        inner: missing,
+                        span,
+                    },
+                ],
+                span,
+            })
+        }
+        Ok(ret)
+    }
+
+    /// Under `:lenient`, replaces each atom reading a stored relation that does not exist
+    /// by one producing no rows, binding the variables of the atom to null to keep the
+    /// rule safe. A negated missing relation always holds, and a top-level
+    /// `optional` one just binds its own variables to null.
+    pub(crate) fn empty_missing_relations(
+        body: Vec<InputAtom>,
+        tx: &SessionTx<'_>,
+    ) -> Result<Vec<InputAtom>> {
+        let mut ret = Vec::with_capacity(body.len());
+        for (i, atom) in body.iter().enumerate() {
+            if let InputAtom::Optional { inner, span } = atom {
+                if let Some(vars) = inner.missing_relation_bindings(tx)? {
+                    let mut outside = BTreeSet::new();
+                    for (j, other) in body.iter().enumerate() {
+                        if i != j {
+                            other.collect_bindings(&mut outside)?;
+                        }
+                    }
+                    ret.extend(
+                        vars.into_iter()
+                            .filter(|var| !outside.contains(var))
+                            .map(|var| Self::null_unification(var, *span)),
+                    );
+                    continue;
+                }
+            }
+            ret.push(atom.clone().empty_if_missing(tx, false)?);
+        }
+        Ok(ret)
+    }
+
+    fn empty_if_missing(self, tx: &SessionTx<'_>, negated: bool) -> Result<Self> {
+        let constant = |val: bool, span| InputAtom::Predicate {
+            inner: Expr::Const {
+                val: DataValue::from(val),
+                span,
+            },
+        };
+        Ok(match self {
+            InputAtom::Negation { inner, span } => {
+                if inner.missing_relation_bindings(tx)?.is_some() {
+                    constant(true, span)
+                } else {
+                    InputAtom::Negation {
+                        inner: Box::new(inner.empty_if_missing(tx, !negated)?),
+                        span,
+                    }
+                }
+            }
+            InputAtom::Conjunction { inner, span } => InputAtom::Conjunction {
+                inner: inner
+                    .into_iter()
+                    .map(|atom| atom.empty_if_missing(tx, negated))
+                    .try_collect()?,
+                span,
+            },
+            InputAtom::Disjunction { inner, span } => InputAtom::Disjunction {
+                inner: inner
+                    .into_iter()
+                    .map(|atom| atom.empty_if_missing(tx, negated))
+                    .try_collect()?,
+                span,
+            },
+            atom => match atom.missing_relation_bindings(tx)? {
+                None => atom,
+                // variables under a negation are bound elsewhere
+                Some(_) if negated => constant(false, atom.span()),
+                Some(vars) => {
+                    let span = atom.span();
+                    let mut inner = vars
+                        .into_iter()
+                        .map(|var| Self::null_unification(var, span))
+                        .collect_vec();
+                    inner.push(constant(false, span));
+                    InputAtom::Conjunction { inner, span }
+                }
+            },
+        })
+    }
+
+    fn missing_relation_bindings(&self, tx: &SessionTx<'_>) -> Result<Option<Vec<Symbol>>> {
+        let (name, args) = match self {
+            InputAtom::NamedFieldRelation { inner } => {
+                (&inner.name, inner.args.values().collect_vec())
+            }
+            InputAtom::Relation { inner } => (&inner.name, inner.args.iter().collect_vec()),
+            _ => return Ok(None),
+        };
+        if tx.relation_exists(name)? {
+            return Ok(None);
+        }
+        Ok(Some(
+            args.into_iter()
+                .filter_map(|arg| match arg {
+                    Expr::Binding { var, .. } if !var.is_ignored_symbol() => Some(var.clone()),
+                    _ => None,
+                })
+                .unique()
+                .collect_vec(),
+        ))
+    }
+
+    fn null_unification(binding: Symbol, span: SourceSpan) -> Self {
+        InputAtom::Unification {
+            inner: Unification {
+                binding,
+                expr: Expr::Const {
+                    val: DataValue::Null,
+                    span,
+                },
+                one_many_unif: false,
+                span,
+            },
+        }
+    }
+
+    fn ignoring_bindings(&self, vars: &BTreeSet<Symbol>) -> Self {
+        let ignore = |arg: &Expr| match arg {
+            Expr::Binding { var, .. } if vars.contains(var) => Expr::Binding {
+                var: Symbol::new("_", var.span),
+                tuple_pos: None,
+            },
+            a => a.clone(),
+        };
+        match self {
+            InputAtom::Rule { inner } => InputAtom::Rule {
+                inner: InputRuleApplyAtom {
+                    args: inner.args.iter().map(ignore).collect(),
+                    ..inner.clone()
+                },
+            },
+            InputAtom::Relation { inner } => InputAtom::Relation {
+                inner: InputRelationApplyAtom {
+                    args: inner.args.iter().map(ignore).collect(),
+                    ..inner.clone()
+                },
+            },
+            InputAtom::NamedFieldRelation { inner } => InputAtom::NamedFieldRelation {
+                inner: InputNamedFieldRelationApplyAtom {
+                    args: inner
+                        .args
+                        .iter()
+                        .map(|(k, v)| (k.clone(), ignore(v)))
+                        .collect(),
+                    ..inner.clone()
+                },
+            },
+            a => a.clone(),
+        }
+    }
+
+    pub(crate) fn collect_bindings(&self, coll: &mut BTreeSet<Symbol>) -> Result<()> {
+        match self {
+            InputAtom::Rule { inner } => {
+                for arg in &inner.args {
+                    arg.collect_bindings(coll)?;
+                }
+            }
+            InputAtom::NamedFieldRelation { inner } => {
+                for arg in inner.args.values() {
+                    arg.collect_bindings(coll)?;
+                }
+            }
+            InputAtom::Relation { inner } => {
+                for arg in &inner.args {
+                    arg.collect_bindings(coll)?;
+                }
+            }
+            InputAtom::Predicate { inner } => inner.collect_bindings(coll)?,
+            InputAtom::Negation { inner, .. } | InputAtom::Optional { inner, .. } => {
+                inner.collect_bindings(coll)?
+            }
+            InputAtom::Conjunction { inner, .. } | InputAtom::Disjunction { inner, .. } => {
+                for a in inner {
+                    a.collect_bindings(coll)?;
+                }
+            }
+            InputAtom::Unification { inner } => {
+                coll.insert(inner.binding.clone());
+                inner.expr.collect_bindings(coll)?;
+            }
+            InputAtom::Search { inner } => {
+                for arg in inner.bindings.values().chain(inner.parameters.values()) {
+                    arg.collect_bindings(coll)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub(crate) fn negation_normal_form(self) -> Result<Self> {
         Ok(match self {
             a @ (InputAtom::Rule { .. }
@@ -124,7 +419,9 @@ impl InputAtom {
                 InputAtom::Search { inner } => {
                     bail!(UnsafeNegation(inner.span))
                 }
+                InputAtom::Optional { span, .. } => bail!(NestedOptional(span)),
             },
+            InputAtom::Optional { span, .. } => bail!(NestedOptional(span)),
             InputAtom::Search { inner } => InputAtom::Search { inner },
         })
     }
@@ -230,6 +527,7 @@ impl InputAtom {
                 Disjunction::singlet(NormalFormAtom::Unification(u))
             }
             InputAtom::Search { inner } => inner.normalize(gen, tx)?,
+            InputAtom::Optional { span, .. } => bail!(NestedOptional(span)),
         })
     }
 }
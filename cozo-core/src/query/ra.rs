@@ -9,6 +9,9 @@
 use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::{Debug, Formatter, Write};
 use std::iter;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use either::{Left, Right};
 use itertools::Itertools;
@@ -18,6 +21,7 @@ use smartstring::SmartString;
 use thiserror::Error;
 
 use crate::data::expr::{compute_bounds, eval_bytecode, eval_bytecode_pred, Bytecode, Expr};
+use crate::data::functions::{OP_GE, OP_GT, OP_LE, OP_LT};
 use crate::data::program::{FtsSearch, HnswSearch, MagicSymbol};
 use crate::data::relation::{ColType, NullableColType};
 use crate::data::symb::Symbol;
@@ -30,12 +34,118 @@ use crate::runtime::temp_store::EpochStore;
 use crate::runtime::transact::SessionTx;
 use crate::utils::swap_option_result;
 
+/// Rows produced and time spent by each node of a relational algebra tree,
+/// keyed by [RelAlgebra::profile_key]. Times are inclusive of the node's children,
+/// and both are summed over every time the node is iterated.
+/// The first `max_samples` tuples produced by each node are kept as well.
+#[derive(Clone, Default)]
+pub(crate) struct QueryProfile {
+    pub(crate) nodes: Arc<Mutex<BTreeMap<usize, NodeProfile>>>,
+    pub(crate) max_samples: usize,
+}
+
+#[derive(Default)]
+pub(crate) struct NodeProfile {
+    pub(crate) rows: usize,
+    pub(crate) elapsed: Duration,
+    pub(crate) samples: Vec<Tuple>,
+}
+
+struct ProfiledIter<'a> {
+    inner: TupleIter<'a>,
+    node: usize,
+    rows: usize,
+    elapsed: Duration,
+    samples: Vec<Tuple>,
+    profile: &'a QueryProfile,
+}
+
+impl Iterator for ProfiledIter<'_> {
+    type Item = Result<Tuple>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = Instant::now();
+        let ret = self.inner.next();
+        self.elapsed += start.elapsed();
+        if let Some(Ok(tuple)) = &ret {
+            self.rows += 1;
+            if self.samples.len() < self.profile.max_samples {
+                self.samples.push(tuple.clone());
+            }
+        }
+        ret
+    }
+}
+
+impl Drop for ProfiledIter<'_> {
+    fn drop(&mut self) {
+        let mut nodes = self.profile.nodes.lock().unwrap();
+        let node = nodes.entry(self.node).or_default();
+        node.rows += self.rows;
+        node.elapsed += self.elapsed;
+        let room = self.profile.max_samples.saturating_sub(node.samples.len());
+        node.samples.extend(self.samples.drain(..).take(room));
+    }
+}
+
+/// A callback invoked after every `every` tuples produced by the body of a rule,
+/// and after every `every` stored tuples scanned in a transaction, so that
+/// callers embedding the engine can hand control back to a scheduler during
+/// long evaluations.
+#[derive(Clone)]
+pub(crate) struct YieldHook {
+    pub(crate) every: usize,
+    pub(crate) hook: Arc<dyn Fn() + Send + Sync>,
+    scanned: Arc<AtomicUsize>,
+}
+
+impl YieldHook {
+    pub(crate) fn new(every: usize, hook: Arc<dyn Fn() + Send + Sync>) -> Self {
+        Self {
+            every: every.max(1),
+            hook,
+            scanned: Default::default(),
+        }
+    }
+    /// The same hook with its own count of scanned tuples, for a new transaction
+    pub(crate) fn for_tx(&self) -> Self {
+        Self::new(self.every, self.hook.clone())
+    }
+    pub(crate) fn count_scanned(&self) {
+        if (self.scanned.fetch_add(1, Ordering::Relaxed) + 1) % self.every == 0 {
+            (self.hook)();
+        }
+    }
+}
+
+struct YieldingIter<'a> {
+    inner: TupleIter<'a>,
+    produced: usize,
+    hook: &'a YieldHook,
+}
+
+impl Iterator for YieldingIter<'_> {
+    type Item = Result<Tuple>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ret = self.inner.next();
+        if let Some(Ok(_)) = &ret {
+            self.produced += 1;
+            if self.produced % self.hook.every == 0 {
+                (self.hook.hook)();
+            }
+        }
+        ret
+    }
+}
+
 pub(crate) enum RelAlgebra {
     Fixed(InlineFixedRA),
     TempStore(TempStoreRA),
     Stored(StoredRA),
     StoredWithValidity(StoredWithValidityRA),
     Join(Box<InnerJoin>),
+    ThetaJoin(Box<ThetaJoin>),
     NegJoin(Box<NegJoin>),
     Reorder(ReorderRA),
     Filter(FilteredRA),
@@ -52,6 +162,7 @@ impl RelAlgebra {
             RelAlgebra::TempStore(i) => i.span,
             RelAlgebra::Stored(i) => i.span,
             RelAlgebra::Join(i) => i.span,
+            RelAlgebra::ThetaJoin(i) => i.span,
             RelAlgebra::NegJoin(i) => i.span,
             RelAlgebra::Reorder(i) => i.relation.span(),
             RelAlgebra::Filter(i) => i.span,
@@ -318,6 +429,14 @@ impl Debug for RelAlgebra {
                         .finish()
                 }
             }
+            RelAlgebra::ThetaJoin(r) => f
+                .debug_tuple("ThetaJoin")
+                .field(&bindings)
+                .field(&r.inner.joiner)
+                .field(&r.theta)
+                .field(&r.inner.left)
+                .field(&r.inner.right)
+                .finish(),
             RelAlgebra::NegJoin(r) => f
                 .debug_tuple("NegJoin")
                 .field(&bindings)
@@ -395,6 +514,11 @@ impl RelAlgebra {
                 r.left.fill_binding_indices_and_compile()?;
                 r.right.fill_binding_indices_and_compile()?;
             }
+            RelAlgebra::ThetaJoin(r) => {
+                r.inner.left.fill_binding_indices_and_compile()?;
+                r.inner.right.fill_binding_indices_and_compile()?;
+                r.fill_binding_indices_and_compile()?;
+            }
         }
         Ok(())
     }
@@ -576,23 +700,68 @@ impl RelAlgebra {
                         remaining.push(filter);
                     }
                 }
-                let mut joined = RelAlgebra::Join(Box::new(InnerJoin {
+                let inner = InnerJoin {
                     left,
                     right,
                     joiner,
                     to_eliminate,
                     span,
-                }));
-                if !remaining.is_empty() {
-                    joined = RelAlgebra::Filter(FilteredRA {
-                        parent: Box::new(joined),
-                        filters: remaining,
-                        filters_bytecodes: vec![],
+                };
+                if remaining.is_empty() {
+                    RelAlgebra::Join(Box::new(inner))
+                } else {
+                    // conditions spanning both sides are evaluated during the join,
+                    // instead of filtering the full product afterwards
+                    RelAlgebra::ThetaJoin(Box::new(ThetaJoin {
+                        inner,
+                        theta: remaining,
+                        theta_bytecodes: vec![],
+                        range_bounds: vec![],
                         to_eliminate: Default::default(),
                         span,
-                    });
+                    }))
+                }
+            }
+            RelAlgebra::ThetaJoin(theta_join) => {
+                let filters = filter.to_conjunction();
+                let left_bindings: BTreeSet<Symbol> = theta_join
+                    .inner
+                    .left
+                    .bindings_before_eliminate()
+                    .into_iter()
+                    .collect();
+                let right_bindings: BTreeSet<Symbol> = theta_join
+                    .inner
+                    .right
+                    .bindings_before_eliminate()
+                    .into_iter()
+                    .collect();
+                let ThetaJoin {
+                    mut inner,
+                    mut theta,
+                    theta_bytecodes,
+                    range_bounds,
+                    to_eliminate,
+                    span,
+                } = *theta_join;
+                for filter in filters {
+                    let f_bindings = filter.bindings()?;
+                    if f_bindings.is_subset(&left_bindings) {
+                        inner.left = inner.left.filter(filter)?;
+                    } else if f_bindings.is_subset(&right_bindings) {
+                        inner.right = inner.right.filter(filter)?;
+                    } else {
+                        theta.push(filter);
+                    }
                 }
-                joined
+                RelAlgebra::ThetaJoin(Box::new(ThetaJoin {
+                    inner,
+                    theta,
+                    theta_bytecodes,
+                    range_bounds,
+                    to_eliminate,
+                    span,
+                }))
             }
         })
     }
@@ -1394,6 +1563,59 @@ impl StoredRA {
         })
     }
 
+    fn range_join<'a>(
+        &'a self,
+        tx: &'a SessionTx<'_>,
+        left_iter: TupleIter<'a>,
+        (left_join_indices, right_join_indices): (Vec<usize>, Vec<usize>),
+        bounds: &'a [RangeJoinBound],
+    ) -> TupleIter<'a> {
+        let mut right_invert_indices = right_join_indices.iter().enumerate().collect_vec();
+        right_invert_indices.sort_by_key(|(_, b)| **b);
+        let left_to_prefix_indices = right_invert_indices
+            .into_iter()
+            .map(|(a, _)| left_join_indices[a])
+            .collect_vec();
+        let prefix_len = left_to_prefix_indices.len();
+        let bounded = &self.bindings[prefix_len..prefix_len + 1];
+
+        let mut stack = vec![];
+        Box::new(
+            left_iter
+                .map(move |tuple| -> Result<_> {
+                    let tuple = tuple?;
+                    let prefix = left_to_prefix_indices
+                        .iter()
+                        .map(|i| tuple[*i].clone())
+                        .collect_vec();
+                    let mut preds = Vec::with_capacity(bounds.len());
+                    for bound in bounds {
+                        let val = eval_bytecode(&bound.left_bytecode, &tuple, &mut stack)?;
+                        preds.push(bound.with_left_value(val));
+                    }
+                    let (l_bound, u_bound) = compute_bounds(&preds, bounded)?;
+                    let mut stack = vec![];
+                    Ok(self
+                        .storage
+                        .scan_bounded_prefix(tx, &prefix, &l_bound, &u_bound)
+                        .map(move |res_found| -> Result<Option<Tuple>> {
+                            let found = res_found?;
+                            for (p, span) in self.filters_bytecodes.iter() {
+                                if !eval_bytecode_pred(p, &found, &mut stack, *span)? {
+                                    return Ok(None);
+                                }
+                            }
+                            let mut ret = tuple.clone();
+                            ret.extend(found);
+                            Ok(Some(ret))
+                        })
+                        .filter_map(swap_option_result))
+                })
+                .flatten_ok()
+                .map(flatten_err),
+        )
+    }
+
     fn neg_join<'a>(
         &'a self,
         tx: &'a SessionTx<'_>,
@@ -1826,6 +2048,7 @@ impl RelAlgebra {
             RelAlgebra::Stored(_v) => Ok(()),
             RelAlgebra::StoredWithValidity(_v) => Ok(()),
             RelAlgebra::Join(r) => r.do_eliminate_temp_vars(used),
+            RelAlgebra::ThetaJoin(r) => r.do_eliminate_temp_vars(used),
             RelAlgebra::Reorder(r) => r.relation.eliminate_temp_vars(used),
             RelAlgebra::Filter(r) => r.do_eliminate_temp_vars(used),
             RelAlgebra::NegJoin(r) => r.do_eliminate_temp_vars(used),
@@ -1843,6 +2066,7 @@ impl RelAlgebra {
             RelAlgebra::Stored(_) => None,
             RelAlgebra::StoredWithValidity(_) => None,
             RelAlgebra::Join(r) => Some(&r.to_eliminate),
+            RelAlgebra::ThetaJoin(r) => Some(&r.to_eliminate),
             RelAlgebra::Reorder(_) => None,
             RelAlgebra::Filter(r) => Some(&r.to_eliminate),
             RelAlgebra::NegJoin(r) => Some(&r.to_eliminate),
@@ -1871,6 +2095,7 @@ impl RelAlgebra {
             RelAlgebra::Stored(v) => v.bindings.clone(),
             RelAlgebra::StoredWithValidity(v) => v.bindings.clone(),
             RelAlgebra::Join(j) => j.bindings(),
+            RelAlgebra::ThetaJoin(j) => j.inner.bindings(),
             RelAlgebra::Reorder(r) => r.bindings(),
             RelAlgebra::Filter(r) => r.parent.bindings_after_eliminate(),
             RelAlgebra::NegJoin(j) => j.left.bindings_after_eliminate(),
@@ -1896,11 +2121,55 @@ impl RelAlgebra {
             }
         }
     }
+    /// Iterate this relation as the body of a rule, calling the yield hook
+    /// of the transaction, if any, as tuples are produced.
+    pub(crate) fn iter_rule_body<'a>(
+        &'a self,
+        tx: &'a SessionTx<'_>,
+        delta_rule: Option<&MagicSymbol>,
+        stores: &'a BTreeMap<MagicSymbol, EpochStore>,
+    ) -> Result<TupleIter<'a>> {
+        let inner = self.iter(tx, delta_rule, stores)?;
+        Ok(match &tx.yield_hook {
+            None => inner,
+            Some(hook) => Box::new(YieldingIter {
+                inner,
+                produced: 0,
+                hook,
+            }),
+        })
+    }
     pub(crate) fn iter<'a>(
         &'a self,
         tx: &'a SessionTx<'_>,
         delta_rule: Option<&MagicSymbol>,
         stores: &'a BTreeMap<MagicSymbol, EpochStore>,
+    ) -> Result<TupleIter<'a>> {
+        match &tx.profile {
+            None => self.iter_unprofiled(tx, delta_rule, stores),
+            Some(profile) => {
+                let start = Instant::now();
+                let inner = self.iter_unprofiled(tx, delta_rule, stores)?;
+                Ok(Box::new(ProfiledIter {
+                    inner,
+                    node: self.profile_key(),
+                    rows: 0,
+                    elapsed: start.elapsed(),
+                    samples: vec![],
+                    profile,
+                }))
+            }
+        }
+    }
+    /// The key under which the stats of this node are collected when profiling.
+    pub(crate) fn profile_key(&self) -> usize {
+        self as *const RelAlgebra as usize
+    }
+    fn iter_unprofiled<'a>(
+        &'a self,
+        tx: &'a SessionTx<'_>,
+        delta_rule: Option<&MagicSymbol>,
+        stores: &'a BTreeMap<MagicSymbol, EpochStore>,
     ) -> Result<TupleIter<'a>> {
         match self {
             RelAlgebra::Fixed(f) => Ok(Box::new(f.data.iter().map(|t| Ok(t.clone())))),
@@ -1908,6 +2177,7 @@ impl RelAlgebra {
             RelAlgebra::Stored(v) => v.iter(tx),
             RelAlgebra::StoredWithValidity(v) => v.iter(tx),
             RelAlgebra::Join(j) => j.iter(tx, delta_rule, stores),
+            RelAlgebra::ThetaJoin(j) => j.iter(tx, delta_rule, stores),
             RelAlgebra::Reorder(r) => r.iter(tx, delta_rule, stores),
             RelAlgebra::Filter(r) => r.iter(tx, delta_rule, stores),
             RelAlgebra::NegJoin(r) => r.iter(tx, delta_rule, stores),
@@ -2111,9 +2381,10 @@ impl InnerJoin {
                     "stored_mat_join"
                 }
             }
-            RelAlgebra::Join(_) | RelAlgebra::Filter(_) | RelAlgebra::Unification(_) => {
-                "generic_mat_join"
-            }
+            RelAlgebra::Join(_)
+            | RelAlgebra::ThetaJoin(_)
+            | RelAlgebra::Filter(_)
+            | RelAlgebra::Unification(_) => "generic_mat_join",
             RelAlgebra::Reorder(_) => {
                 panic!("joining on reordered")
             }
@@ -2204,6 +2475,7 @@ impl InnerJoin {
                 }
             }
             RelAlgebra::Join(_)
+            | RelAlgebra::ThetaJoin(_)
             | RelAlgebra::Filter(_)
             | RelAlgebra::Unification(_)
             | RelAlgebra::HnswSearch(_)
@@ -2288,6 +2560,171 @@ impl InnerJoin {
     }
 }
 
+/// An inner join with additional conditions relating bindings from both sides,
+/// e.g. `?d1 < ?d2`. The conditions are checked as each joined tuple is produced.
+/// When the right side is a stored relation and some conditions bound the key column
+/// right after the join prefix by the left bindings, each left tuple drives a range scan.
+#[derive(Debug)]
+pub(crate) struct ThetaJoin {
+    pub(crate) inner: InnerJoin,
+    pub(crate) theta: Vec<Expr>,
+    pub(crate) theta_bytecodes: Vec<(Vec<Bytecode>, SourceSpan)>,
+    pub(crate) range_bounds: Vec<RangeJoinBound>,
+    pub(crate) to_eliminate: BTreeSet<Symbol>,
+    pub(crate) span: SourceSpan,
+}
+
+/// A comparison between the range-scanned column and an expression of the left bindings.
+#[derive(Debug)]
+pub(crate) struct RangeJoinBound {
+    pred: Expr,
+    left_pos: usize,
+    left_bytecode: Vec<Bytecode>,
+}
+
+impl RangeJoinBound {
+    fn with_left_value(&self, val: DataValue) -> Expr {
+        let mut pred = self.pred.clone();
+        if let Expr::Apply { args, span, .. } = &mut pred {
+            args[self.left_pos] = Expr::Const { val, span: *span };
+        }
+        pred
+    }
+}
+
+impl ThetaJoin {
+    pub(crate) fn do_eliminate_temp_vars(&mut self, used: &BTreeSet<Symbol>) -> Result<()> {
+        for binding in self.inner.bindings() {
+            if !used.contains(&binding) {
+                self.to_eliminate.insert(binding.clone());
+            }
+        }
+        let mut nxt = used.clone();
+        for e in self.theta.iter() {
+            nxt.extend(e.bindings()?);
+        }
+        self.inner.do_eliminate_temp_vars(&nxt)?;
+        // the conditions need the full joined tuple, elimination happens afterwards
+        self.inner.to_eliminate.clear();
+        Ok(())
+    }
+
+    fn range_column(&self) -> Option<Symbol> {
+        if let RelAlgebra::Stored(r) = &self.inner.right {
+            let (_, right_join_indices) = self
+                .inner
+                .joiner
+                .join_indices(&self.inner.left.bindings_after_eliminate(), &r.bindings)
+                .ok()?;
+            if join_is_prefix(&right_join_indices)
+                && right_join_indices.len() < r.storage.metadata.keys.len()
+            {
+                return r.bindings.get(right_join_indices.len()).cloned();
+            }
+        }
+        None
+    }
+
+    fn fill_binding_indices_and_compile(&mut self) -> Result<()> {
+        let bindings: BTreeMap<_, _> = self
+            .inner
+            .bindings()
+            .into_iter()
+            .enumerate()
+            .map(|(a, b)| (b, a))
+            .collect();
+        for e in self.theta.iter_mut() {
+            e.fill_binding_indices(&bindings)?;
+            self.theta_bytecodes.push((e.compile()?, e.span()));
+        }
+        if let Some(col) = self.range_column() {
+            let left_bindings: BTreeMap<_, _> = self
+                .inner
+                .left
+                .bindings_after_eliminate()
+                .into_iter()
+                .enumerate()
+                .map(|(a, b)| (b, a))
+                .collect();
+            for e in self.theta.iter() {
+                if let Expr::Apply { op, args, .. } = e {
+                    if ![OP_GE.name, OP_GT.name, OP_LE.name, OP_LT.name].contains(&op.name) {
+                        continue;
+                    }
+                    for (bounded_pos, left_pos) in [(0, 1), (1, 0)] {
+                        if args[bounded_pos].get_binding() != Some(&col) {
+                            continue;
+                        }
+                        let left_expr_bindings = args[left_pos].bindings()?;
+                        if left_expr_bindings
+                            .iter()
+                            .all(|b| left_bindings.contains_key(b))
+                        {
+                            let mut left_expr = args[left_pos].clone();
+                            left_expr.fill_binding_indices(&left_bindings)?;
+                            self.range_bounds.push(RangeJoinBound {
+                                pred: e.clone(),
+                                left_pos,
+                                left_bytecode: left_expr.compile()?,
+                            });
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn join_type(&self) -> &str {
+        if self.range_bounds.is_empty() {
+            "theta_join"
+        } else {
+            "stored_range_join"
+        }
+    }
+
+    pub(crate) fn iter<'a>(
+        &'a self,
+        tx: &'a SessionTx<'_>,
+        delta_rule: Option<&MagicSymbol>,
+        stores: &'a BTreeMap<MagicSymbol, EpochStore>,
+    ) -> Result<TupleIter<'a>> {
+        let bindings = self.inner.bindings();
+        let eliminate_indices = get_eliminate_indices(&bindings, &self.to_eliminate);
+        let joined = match &self.inner.right {
+            RelAlgebra::Stored(r) if !self.range_bounds.is_empty() => {
+                let join_indices = self
+                    .inner
+                    .joiner
+                    .join_indices(&self.inner.left.bindings_after_eliminate(), &r.bindings)
+                    .unwrap();
+                r.range_join(
+                    tx,
+                    self.inner.left.iter(tx, delta_rule, stores)?,
+                    join_indices,
+                    &self.range_bounds,
+                )
+            }
+            _ => self.inner.iter(tx, delta_rule, stores)?,
+        };
+        let mut stack = vec![];
+        Ok(Box::new(joined.filter_map(move |tuple| match tuple {
+            Ok(t) => {
+                for (p, span) in self.theta_bytecodes.iter() {
+                    match eval_bytecode_pred(p, &t, &mut stack, *span) {
+                        Ok(false) => return None,
+                        Err(e) => return Some(Err(e)),
+                        Ok(true) => {}
+                    }
+                }
+                Some(Ok(eliminate_from_tuple(t, &eliminate_indices)))
+            }
+            Err(e) => Some(Err(e)),
+        })))
+    }
+}
+
 struct CachedMaterializedIterator<'a> {
     materialized: Vec<Tuple>,
     eliminate_indices: BTreeSet<usize>,
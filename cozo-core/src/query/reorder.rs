@@ -9,10 +9,12 @@
 use std::collections::BTreeSet;
 use std::mem;
 
+use itertools::Itertools;
 use miette::{bail, Diagnostic, Result};
 use thiserror::Error;
 
 use crate::data::program::{NormalFormAtom, NormalFormInlineRule};
+use crate::data::symb::Symbol;
 use crate::parse::SourceSpan;
 
 #[derive(Diagnostic, Debug, Error)]
@@ -25,6 +27,15 @@ You may also encounter this error if your rule can never produce any rows."
 ))]
 pub(crate) struct UnsafeNegation(#[label] pub(crate) SourceSpan);
 
+#[derive(Diagnostic, Debug, Error)]
+#[error("Negated atom shares no bound variable with the rest of the rule: {0}")]
+#[diagnostic(code(eval::unsafe_negation))]
+#[diagnostic(help(
+    "At least one variable of a negated atom must be bound by a positive atom \
+or unification of the same rule"
+))]
+pub(crate) struct UnboundNegation(String, #[label] SourceSpan);
+
 #[derive(Diagnostic, Debug, Error)]
 #[error("Atom contains unbound variable, or rule contains no variable at all")]
 #[diagnostic(code(eval::unbound_variable))]
@@ -187,6 +198,7 @@ impl NormalFormInlineRule {
                     }
                     NormalFormAtom::Unification(u) => {
                         if u.bindings_in_expr()?.is_subset(&seen_variables) {
+                            seen_variables.insert(u.binding.clone());
                             collected.push(NormalFormAtom::Unification(u.clone()));
                         } else {
                             pending.push(NormalFormAtom::Unification(u.clone()));
@@ -204,14 +216,14 @@ impl NormalFormInlineRule {
                         if r.args.iter().any(|a| seen_variables.contains(a)) {
                             collected.push(NormalFormAtom::NegatedRule(r.clone()));
                         } else {
-                            bail!(UnsafeNegation(r.span));
+                            bail!(UnboundNegation(display_args(&r.args), r.span));
                         }
                     }
                     NormalFormAtom::NegatedRelation(v) => {
                         if v.args.iter().any(|a| seen_variables.contains(a)) {
                             collected.push(NormalFormAtom::NegatedRelation(v.clone()));
                         } else {
-                            bail!(UnsafeNegation(v.span));
+                            bail!(UnboundNegation(display_args(&v.args), v.span));
                         }
                     }
                     NormalFormAtom::Predicate(p) => {
@@ -240,3 +252,10 @@ impl NormalFormInlineRule {
         })
     }
 }
+
+fn display_args(args: &[Symbol]) -> String {
+    args.iter()
+        .filter(|a| !a.is_generated_ignored_symbol())
+        .map(|a| a.name.as_str())
+        .join(", ")
+}
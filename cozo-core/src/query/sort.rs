@@ -11,10 +11,13 @@ use std::collections::BTreeMap;
 
 use itertools::Itertools;
 use miette::Result;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
 
-use crate::data::program::SortDir;
+use crate::data::program::{Collation, SortDir};
 use crate::data::symb::Symbol;
 use crate::data::tuple::Tuple;
+use crate::data::value::DataValue;
 use crate::runtime::temp_store::EpochStore;
 use crate::runtime::transact::SessionTx;
 
@@ -22,19 +25,38 @@ impl<'a> SessionTx<'a> {
     pub(crate) fn sort_and_collect(
         &mut self,
         original: EpochStore,
-        sorters: &[(Symbol, SortDir)],
+        sorters: &[(Symbol, SortDir, Collation)],
         head: &[Symbol],
     ) -> Result<Vec<Tuple>> {
         let head_indices: BTreeMap<_, _> = head.iter().enumerate().map(|(i, k)| (k, i)).collect();
         let idx_sorters = sorters
             .iter()
-            .map(|(k, dir)| (head_indices[k], *dir))
+            .map(|(k, dir, collation)| (head_indices[k], *dir, *collation))
             .collect_vec();
 
-        let mut all_data: Vec<_> = original.all_iter().map(|v| v.into_tuple()).collect_vec();
-        all_data.sort_by(|a, b| {
-            for (idx, dir) in &idx_sorters {
-                match a[*idx].cmp(&b[*idx]) {
+        // Unicode collation keys are costly to build, so compute them once per
+        // row rather than on every comparison.
+        let mut keyed: Vec<_> = original
+            .all_iter()
+            .map(|v| {
+                let tuple = v.into_tuple();
+                let keys = idx_sorters
+                    .iter()
+                    .map(|(idx, _, collation)| match (collation, &tuple[*idx]) {
+                        (Collation::Unicode, DataValue::Str(s)) => Some(CollationKey::new(s)),
+                        _ => None,
+                    })
+                    .collect_vec();
+                (keys, tuple)
+            })
+            .collect_vec();
+        keyed.sort_by(|(a_keys, a), (b_keys, b)| {
+            for (i, (idx, dir, _)) in idx_sorters.iter().enumerate() {
+                let ord = match (&a_keys[i], &b_keys[i]) {
+                    (Some(x), Some(y)) => x.cmp(y).then_with(|| a[*idx].cmp(&b[*idx])),
+                    _ => a[*idx].cmp(&b[*idx]),
+                };
+                match ord {
                     Ordering::Equal => {}
                     o => {
                         return match dir {
@@ -46,7 +68,33 @@ impl<'a> SessionTx<'a> {
             }
             Ordering::Equal
         });
+        let all_data = keyed.into_iter().map(|(_, tuple)| tuple).collect_vec();
 
         Ok(all_data)
     }
 }
+
+/// Sort key for a simplified multi-level collation in the spirit of the Unicode
+/// Collation Algorithm: base letters are compared first ignoring case, then
+/// accents, then case with lower case first. Callers break any remaining ties
+/// by code point order.
+#[derive(Eq, PartialEq, Ord, PartialOrd)]
+struct CollationKey {
+    base: Vec<char>,
+    accented: Vec<char>,
+    cased: Vec<bool>,
+}
+
+impl CollationKey {
+    fn new(s: &str) -> Self {
+        Self {
+            base: s
+                .nfd()
+                .filter(|c| !is_combining_mark(*c))
+                .flat_map(char::to_lowercase)
+                .collect(),
+            accented: s.nfd().flat_map(char::to_lowercase).collect(),
+            cased: s.nfd().map(char::is_uppercase).collect(),
+        }
+    }
+}
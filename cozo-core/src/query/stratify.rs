@@ -7,10 +7,10 @@
  */
 
 use std::collections::btree_map::Entry;
-use std::collections::{BTreeMap, BTreeSet};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 
 use itertools::Itertools;
-use miette::{ensure, Diagnostic, Result};
+use miette::{bail, Diagnostic, Result};
 use thiserror::Error;
 
 use crate::data::program::{
@@ -167,17 +167,22 @@ fn verify_no_cycle(g: &StratifiedGraph<&'_ Symbol>, sccs: &[BTreeSet<&Symbol>])
                     #[diagnostic(help(
                         "The rule '{0}' is in the strongly connected component {1:?},\n\
                     and is involved in at least one forbidden dependency \n\
-                    (negation, non-meet aggregation, or algorithm-application)."
+                    (negation, non-meet aggregation, or algorithm-application).\n\
+                    The offending cycle is {2}."
                     ))]
-                    struct UnStratifiableProgram(String, Vec<String>);
+                    struct UnStratifiableProgram(String, Vec<String>, String);
 
-                    ensure!(
-                        !negated || !scc.contains(v),
-                        UnStratifiableProgram(
+                    if *negated && scc.contains(v) {
+                        let cycle = find_cycle(g, scc, k, v)
+                            .iter()
+                            .map(|s| s.to_string())
+                            .join(" -> ");
+                        bail!(UnStratifiableProgram(
                             v.to_string(),
-                            scc.iter().map(|v| v.to_string()).collect_vec()
-                        )
-                    );
+                            scc.iter().map(|v| v.to_string()).collect_vec(),
+                            cycle
+                        ));
+                    }
                 }
             }
         }
@@ -185,6 +190,42 @@ fn verify_no_cycle(g: &StratifiedGraph<&'_ Symbol>, sccs: &[BTreeSet<&Symbol>])
     Ok(())
 }
 
+/// Finds the shortest path of dependencies `from -> to -> ... -> from` within the SCC.
+fn find_cycle<'a>(
+    g: &StratifiedGraph<&'a Symbol>,
+    scc: &BTreeSet<&'a Symbol>,
+    from: &'a Symbol,
+    to: &'a Symbol,
+) -> Vec<&'a Symbol> {
+    let mut parents: BTreeMap<&Symbol, &Symbol> = BTreeMap::new();
+    let mut queue = VecDeque::from([to]);
+    while let Some(cur) = queue.pop_front() {
+        if cur == from {
+            break;
+        }
+        for nxt in g.get(cur).into_iter().flat_map(|vs| vs.keys()) {
+            if scc.contains(nxt) && *nxt != to && !parents.contains_key(nxt) {
+                parents.insert(nxt, cur);
+                queue.push_back(nxt);
+            }
+        }
+    }
+    let mut path = vec![from];
+    let mut cur = from;
+    while cur != to {
+        match parents.get(cur) {
+            Some(p) => {
+                path.push(p);
+                cur = p;
+            }
+            None => break,
+        }
+    }
+    path.push(from);
+    path.reverse();
+    path
+}
+
 fn make_scc_reduced_graph(
     sccs: &[BTreeSet<&Symbol>],
     graph: &StratifiedGraph<&Symbol>,
@@ -333,4 +374,62 @@ mod tests {
             .rows;
         // dbg!(res);
     }
+
+    #[test]
+    fn test_unstratifiable_cycle() {
+        let db = DbInstance::default();
+        let err = db
+            .run_default(
+                r#"
+        edge[a, b] <- [[1, 2], [2, 3]]
+        reach[a, b] := edge[a, b]
+        reach[a, b] := total[a, n], edge[b, _], b <= n
+        total[a, count(b)] := reach[a, b]
+        ?[a, n] := total[a, n]
+        "#,
+            )
+            .unwrap_err();
+        let help = err.help().unwrap().to_string();
+        assert!(help.contains("total -> reach -> total"), "{}", help);
+    }
+
+    #[test]
+    fn test_stratified_negation() {
+        let db = DbInstance::default();
+        let res = db
+            .run_default(
+                r#"
+        edge[a, b] <- [[1, 2], [2, 3], [4, 5]]
+        node[a] := edge[a, _]
+        node[a] := edge[_, a]
+        reach[b] := edge[1, b]
+        reach[b] := reach[a], edge[a, b]
+        ?[a] := node[a], not reach[a]
+        "#,
+            )
+            .unwrap();
+        assert_eq!(res.into_json()["rows"], serde_json::json!([[1], [4], [5]]));
+    }
+
+    #[test]
+    fn test_negation_in_cycle() {
+        let db = DbInstance::default();
+        let err = db
+            .run_default(
+                r#"
+        node[a] <- [[1], [2]]
+        p[a] := node[a], not q[a]
+        q[a] := node[a], not p[a]
+        ?[a] := p[a]
+        "#,
+            )
+            .unwrap_err();
+        assert_eq!(err.code().unwrap().to_string(), "eval::unstratifiable");
+        let help = err.help().unwrap().to_string();
+        assert!(
+            help.contains("p -> q -> p") || help.contains("q -> p -> q"),
+            "{}",
+            help
+        );
+    }
 }
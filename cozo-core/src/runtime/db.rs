@@ -6,6 +6,7 @@
  * You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+use std::cmp::Reverse;
 use std::collections::btree_map::Entry;
 use std::collections::{BTreeMap, BTreeSet};
 use std::default::Default;
@@ -34,18 +35,21 @@ use thiserror::Error;
 
 use crate::data::functions::current_validity;
 use crate::data::json::JsonValue;
-use crate::data::program::{InputProgram, QueryAssertion, RelationOp, ReturnMutation};
-use crate::data::relation::ColumnDef;
+use crate::data::program::{
+    InputProgram, QueryAssertion, QueryOutOptions, RelationOp, ReturnMutation,
+};
+use crate::data::relation::{ColType, ColumnDef, RelationDef};
 use crate::data::tuple::{Tuple, TupleT};
-use crate::data::value::{DataValue, ValidityTs, LARGEST_UTF_CHAR};
+use crate::data::value::{DataValue, Validity, ValidityTs, LARGEST_UTF_CHAR};
 use crate::fixed_rule::DEFAULT_FIXED_RULES;
 use crate::fts::TokenizerCache;
 use crate::parse::sys::SysOp;
 use crate::parse::{parse_expressions, parse_script, CozoScript, SourceSpan};
 use crate::query::compile::{CompiledProgram, CompiledRule, CompiledRuleSet};
 use crate::query::ra::{
-    FilteredRA, FtsSearchRA, HnswSearchRA, InnerJoin, LshSearchRA, NegJoin, RelAlgebra, ReorderRA,
-    StoredRA, StoredWithValidityRA, TempStoreRA, UnificationRA,
+    FilteredRA, FtsSearchRA, HnswSearchRA, InnerJoin, LshSearchRA, NegJoin, NodeProfile,
+    QueryProfile, RelAlgebra, ReorderRA, StoredRA, StoredWithValidityRA, TempStoreRA, ThetaJoin,
+    UnificationRA, YieldHook,
 };
 #[allow(unused_imports)]
 use crate::runtime::callback::{
@@ -54,6 +58,7 @@ use crate::runtime::callback::{
 use crate::runtime::relation::{
     extend_tuple_from_v, AccessLevel, InsufficientAccessLevel, RelationHandle, RelationId,
 };
+use crate::runtime::temp_store::EpochStore;
 use crate::runtime::transact::SessionTx;
 use crate::storage::temp::TempStorage;
 use crate::storage::Storage;
@@ -92,6 +97,18 @@ pub enum ScriptMutability {
     Immutable,
 }
 
+/// How the rows given to [Db::import_relations_with_mode] are applied.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum ImportMode {
+    /// Rows are upserted, replacing any existing rows with the same keys.
+    #[default]
+    Put,
+    /// Rows are inserted, and it is an error if a row with the same keys already exists.
+    Insert,
+    /// Rows with the given keys are removed, other columns may be omitted.
+    Rm,
+}
+
 /// The database object of Cozo.
 #[derive(Clone)]
 pub struct Db<S> {
@@ -107,8 +124,12 @@ pub struct Db<S> {
     #[cfg(not(target_arch = "wasm32"))]
     pub(crate) event_callbacks: Arc<ShardedLock<EventCallbackRegistry>>,
     relation_locks: Arc<ShardedLock<BTreeMap<SmartString<LazyCompact>, Arc<ShardedLock<()>>>>>,
+    clock: Arc<ShardedLock<Clock>>,
+    yield_hook: Arc<ShardedLock<Option<YieldHook>>>,
 }
 
+type Clock = Box<dyn Fn() -> ValidityTs + Send + Sync>;
+
 impl<S> Debug for Db<S> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "Db")
@@ -125,6 +146,40 @@ pub(crate) struct BadDbInit(#[help] pub(crate) String);
 #[diagnostic(code(tx::import_into_index))]
 pub(crate) struct ImportIntoIndex(pub(crate) String);
 
+#[derive(Debug, Error, Diagnostic)]
+#[error("Relation '{0}' is not versioned: its last key column is not of type Validity")]
+#[diagnostic(code(eval::relation_not_versioned))]
+pub(crate) struct RelationNotVersioned(pub(crate) String);
+
+#[derive(Debug, Error, Diagnostic)]
+#[error("Relation '{0}' expects {1} key value(s), but {2} were given")]
+#[diagnostic(code(eval::key_arity_mismatch))]
+pub(crate) struct KeyArityMismatch(pub(crate) String, pub(crate) usize, pub(crate) usize);
+
+#[derive(Debug, Error, Diagnostic)]
+#[error("Column '{1}' not found in relation '{0}'")]
+#[diagnostic(code(eval::column_not_found))]
+pub(crate) struct ColumnNotFound(pub(crate) String, pub(crate) String);
+
+/// Tuples sampled from one node of a query plan by [`Db::trace_query`].
+/// The node is the row of the analyzed plan with the same `stratum`,
+/// `rule_idx` and `atom_idx`.
+#[derive(Debug, Clone)]
+pub struct NodeTrace {
+    /// The stratum of the node
+    pub stratum: usize,
+    /// The name of the rule the node belongs to
+    pub rule: String,
+    /// The index of the rule among the rules with the same name
+    pub rule_idx: usize,
+    /// The index of the node within the rule
+    pub atom_idx: usize,
+    /// The operation of the node, as shown in the plan
+    pub op: String,
+    /// The first tuples produced by the node
+    pub samples: Vec<Tuple>,
+}
+
 #[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone, Default)]
 /// Rows in a relation, together with headers for the fields.
 pub struct NamedRows {
@@ -184,6 +239,39 @@ impl NamedRows {
             "next": nxt,
         })
     }
+    /// Convert to a JSON object grouping the rows by the values of the column
+    /// `key`: each value maps to the list of rows having it, as objects keyed by
+    /// the remaining headers. Values that are not strings become their JSON text.
+    /// Any `next` named rows are ignored.
+    pub fn nest_by(self, key: &str) -> Result<JsonValue> {
+        let key_idx = self
+            .headers
+            .iter()
+            .position(|h| h == key)
+            .ok_or_else(|| miette!("cannot nest by '{key}': no such column"))?;
+        let mut groups = serde_json::Map::new();
+        for row in self.rows {
+            let group = match &row[key_idx] {
+                DataValue::Str(s) => s.to_string(),
+                v => JsonValue::from(v.clone()).to_string(),
+            };
+            let obj: serde_json::Map<_, _> = self
+                .headers
+                .iter()
+                .zip(row)
+                .enumerate()
+                .filter(|(i, _)| *i != key_idx)
+                .map(|(_, (h, v))| (h.clone(), JsonValue::from(v)))
+                .collect();
+            groups
+                .entry(group)
+                .or_insert_with(|| json!([]))
+                .as_array_mut()
+                .unwrap()
+                .push(JsonValue::Object(obj));
+        }
+        Ok(JsonValue::Object(groups))
+    }
     /// Make named rows from JSON
     pub fn from_json(value: &JsonValue) -> Result<Self> {
         let headers = value
@@ -238,6 +326,17 @@ pub enum TransactionPayload {
     Query((String, BTreeMap<String, DataValue>)),
 }
 
+impl<S> Db<S>
+where
+    S: for<'s> Storage<'s>,
+{
+    /// Flush and release this handle to the database. Storage is closed
+    /// once every clone of the handle has been dropped.
+    pub fn close(self) -> Result<()> {
+        self.db.flush()
+    }
+}
+
 impl<'s, S: Storage<'s>> Db<S> {
     /// Create a new database object with the given storage.
     /// You must call [`initialize`](Self::initialize) immediately after creation.
@@ -257,6 +356,8 @@ impl<'s, S: Storage<'s>> Db<S> {
             #[cfg(not(target_arch = "wasm32"))]
             event_callbacks: Default::default(),
             relation_locks: Default::default(),
+            clock: Arc::new(ShardedLock::new(Box::new(current_validity))),
+            yield_hook: Default::default(),
         };
         Ok(ret)
     }
@@ -294,7 +395,7 @@ impl<'s, S: Storage<'s>> Db<S> {
             }
         };
 
-        let ts = current_validity();
+        let ts = self.now();
         let callback_targets = self.current_callback_targets();
         let mut callback_collector = BTreeMap::new();
         let mut write_locks = BTreeMap::new();
@@ -380,7 +481,7 @@ impl<'s, S: Storage<'s>> Db<S> {
         params: BTreeMap<String, DataValue>,
         mutability: ScriptMutability,
     ) -> Result<NamedRows> {
-        let cur_vld = current_validity();
+        let cur_vld = self.now();
         self.do_run_script(
             payload,
             &params,
@@ -388,15 +489,221 @@ impl<'s, S: Storage<'s>> Db<S> {
             mutability == ScriptMutability::Immutable,
         )
     }
+    /// Run the CozoScript passed in as of the validity `valid_at` instead of the
+    /// current time: `'NOW'` in the script, including reads with `@ 'NOW'` and
+    /// writes with `'ASSERT'` or `'RETRACT'`, refers to `valid_at`.
+    pub fn run_script_at(
+        &'s self,
+        payload: &str,
+        params: BTreeMap<String, DataValue>,
+        valid_at: ValidityTs,
+        mutability: ScriptMutability,
+    ) -> Result<NamedRows> {
+        self.do_run_script(
+            payload,
+            &params,
+            valid_at,
+            mutability == ScriptMutability::Immutable,
+        )
+    }
     /// Run the CozoScript passed in. The `params` argument is a map of parameters.
     pub fn run_script_read_only(
         &'s self,
         payload: &str,
         params: BTreeMap<String, DataValue>,
     ) -> Result<NamedRows> {
-        let cur_vld = current_validity();
+        let cur_vld = self.now();
         self.do_run_script(payload, &params, cur_vld, true)
     }
+    /// Run several read-only queries against a single consistent snapshot,
+    /// returning their results in order. The `params` are shared by all scripts.
+    /// Imperative scripts and system ops are not supported.
+    pub fn run_scripts(
+        &'s self,
+        payloads: &[&str],
+        params: BTreeMap<String, DataValue>,
+    ) -> Result<Vec<NamedRows>> {
+        #[derive(Debug, Error, Diagnostic)]
+        #[error("Only read-only queries can be batched, script {0} is not one")]
+        #[diagnostic(code(eval::batch_not_read_only))]
+        struct BatchNotReadOnly(usize);
+
+        let cur_vld = self.now();
+        let programs: Vec<_> = payloads
+            .iter()
+            .enumerate()
+            .map(|(i, payload)| -> Result<InputProgram> {
+                match parse_script(payload, &params, &self.fixed_rules.read().unwrap(), cur_vld)? {
+                    CozoScript::Single(p) if p.needs_write_lock().is_none() => Ok(p),
+                    _ => bail!(BatchNotReadOnly(i)),
+                }
+            })
+            .try_collect()?;
+
+        let mut tx = self.transact()?;
+        let mut cleanups = vec![];
+        let mut ret = Vec::with_capacity(programs.len());
+        for p in programs {
+            ret.push(self.execute_single_program(
+                p,
+                &mut tx,
+                &mut cleanups,
+                cur_vld,
+                &Default::default(),
+                &mut Default::default(),
+            )?);
+        }
+        for (lower, upper) in cleanups {
+            tx.store_tx.del_range_from_persisted(&lower, &upper)?;
+        }
+        tx.commit_tx()?;
+        Ok(ret)
+    }
+
+    /// Run a read-only query and return only the number of rows it would produce.
+    /// The rows are counted where they are stored, without being collected:
+    /// `:sort` is skipped since it cannot change the count, and with `:limit`
+    /// and no `:sort`, evaluation stops as soon as enough rows are found.
+    pub fn count_query(
+        &'s self,
+        payload: &str,
+        params: BTreeMap<String, DataValue>,
+    ) -> Result<usize> {
+        #[derive(Debug, Error, Diagnostic)]
+        #[error("Only read-only queries can be counted")]
+        #[diagnostic(code(eval::count_not_read_only))]
+        struct CountNotReadOnly;
+
+        let cur_vld = self.now();
+        let program =
+            match parse_script(payload, &params, &self.fixed_rules.read().unwrap(), cur_vld)? {
+                CozoScript::Single(p) if p.needs_write_lock().is_none() => p,
+                _ => bail!(CountNotReadOnly),
+            };
+
+        let mut tx = self.transact()?;
+        let (result_store, early_return, out_opts) = self.evaluate_read_only(program, &mut tx)?;
+        let count = if early_return {
+            result_store.early_returned_iter().count()
+        } else {
+            result_store
+                .all_iter()
+                .count()
+                .saturating_sub(out_opts.offset.unwrap_or(0))
+        };
+        tx.commit_tx()?;
+
+        Ok(match out_opts.limit {
+            Some(limit) => count.min(limit),
+            None => count,
+        })
+    }
+
+    /// Evaluate a read-only program, leaving its rows in the returned store.
+    /// Assertions are checked, but `:sort`, `:offset` and `:limit` are left to the caller,
+    /// except that without `:sort`, evaluation may return early with `:offset` applied.
+    fn evaluate_read_only(
+        &'s self,
+        program: InputProgram,
+        tx: &mut SessionTx<'_>,
+    ) -> Result<(EpochStore, bool, QueryOutOptions)> {
+        let (normalized_program, out_opts) = program.into_normalized_program(tx)?;
+        tx.aggr_limits = out_opts.aggr_limits();
+        let (stratified_program, store_lifetimes) = normalized_program.into_stratified_program()?;
+        let program = stratified_program.magic_sets_rewrite(tx)?;
+        let compiled = tx.stratified_magic_compile(program)?;
+        let poison = Poison::default();
+        if let Some(secs) = out_opts.timeout {
+            poison.set_timeout(secs)?;
+        }
+        let (total_num_to_take, num_to_skip) = if out_opts.sorters.is_empty() {
+            (out_opts.num_to_take(), out_opts.offset)
+        } else {
+            (None, None)
+        };
+        let (result_store, early_return) = tx.stratified_magic_evaluate(
+            &compiled,
+            store_lifetimes,
+            total_num_to_take,
+            num_to_skip,
+            poison,
+        )?;
+        check_assertion(&out_opts, &result_store)?;
+        Ok((result_store, early_return, out_opts))
+    }
+
+    /// Run a read-only query and return its `::explain` plan, with each node
+    /// annotated with the rows it actually produced (`actual_rows`) and the
+    /// milliseconds spent producing them, children included (`ms`).
+    /// Nodes that are never iterated on their own, such as a stored relation
+    /// looked up by a join, have nulls for both.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn run_script_explain_analyze(
+        &'s self,
+        payload: &str,
+        params: BTreeMap<String, DataValue>,
+    ) -> Result<NamedRows> {
+        let (compiled, profile) = self.analyze(payload, params, 0)?;
+        let profile = profile.nodes.lock().unwrap();
+        let (plan, _) = self.explain_compiled(&compiled, Some(&profile))?;
+        Ok(plan)
+    }
+
+    /// Like [`Db::run_script_explain_analyze`], but also keep the first
+    /// `max_samples` tuples produced by each node of the plan, for debugging
+    /// queries that return unexpected rows.
+    /// Returns the analyzed plan together with a trace for every node that
+    /// produced any tuples.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn trace_query(
+        &'s self,
+        payload: &str,
+        params: BTreeMap<String, DataValue>,
+        max_samples: usize,
+    ) -> Result<(NamedRows, Vec<NodeTrace>)> {
+        let (compiled, profile) = self.analyze(payload, params, max_samples)?;
+        let profile = profile.nodes.lock().unwrap();
+        self.explain_compiled(&compiled, Some(&profile))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn analyze(
+        &'s self,
+        payload: &str,
+        params: BTreeMap<String, DataValue>,
+        max_samples: usize,
+    ) -> Result<(Vec<CompiledProgram>, QueryProfile)> {
+        #[derive(Debug, Error, Diagnostic)]
+        #[error("Only read-only queries can be analyzed")]
+        #[diagnostic(code(eval::analyze_not_read_only))]
+        struct AnalyzeNotReadOnly;
+
+        let cur_vld = self.now();
+        let program =
+            match parse_script(payload, &params, &self.fixed_rules.read().unwrap(), cur_vld)? {
+                CozoScript::Single(p) if p.needs_write_lock().is_none() => p,
+                _ => bail!(AnalyzeNotReadOnly),
+            };
+
+        let profile = QueryProfile {
+            max_samples,
+            ..Default::default()
+        };
+        let mut tx = self.transact()?;
+        tx.profile = Some(profile.clone());
+        let (normalized_program, out_opts) = program.into_normalized_program(&tx)?;
+        tx.aggr_limits = out_opts.aggr_limits();
+        let (stratified_program, store_lifetimes) = normalized_program.into_stratified_program()?;
+        let program = stratified_program.magic_sets_rewrite(&tx)?;
+        let compiled = tx.stratified_magic_compile(program)?;
+        let poison = Poison::default();
+        if let Some(secs) = out_opts.timeout {
+            poison.set_timeout(secs)?;
+        }
+        tx.stratified_magic_evaluate(&compiled, store_lifetimes, None, None, poison)?;
+        tx.commit_tx()?;
+        Ok((compiled, profile))
+    }
 
     /// Export relations to JSON data.
     ///
@@ -449,6 +756,199 @@ impl<'s, S: Storage<'s>> Db<S> {
         }
         Ok(ret)
     }
+    /// Read a single value of a stored relation by its full key, without going through a query.
+    /// Returns `None` if no row is stored under `key`. The key values are coerced to the
+    /// types of the key columns, as they would be for `:put`.
+    pub fn get_value(
+        &'s self,
+        relation: &str,
+        key: &[DataValue],
+        column: &str,
+    ) -> Result<Option<DataValue>> {
+        let tx = self.transact()?;
+        let handle = tx.get_relation(relation, false)?;
+        if handle.access_level < AccessLevel::ReadOnly {
+            bail!(InsufficientAccessLevel(
+                handle.name.to_string(),
+                "value lookup".to_string(),
+                handle.access_level
+            ));
+        }
+        ensure!(
+            key.len() == handle.metadata.keys.len(),
+            KeyArityMismatch(relation.to_string(), handle.metadata.keys.len(), key.len())
+        );
+        let col_idx = handle
+            .metadata
+            .keys
+            .iter()
+            .chain(handle.metadata.non_keys.iter())
+            .position(|col| col.name == column)
+            .ok_or_else(|| ColumnNotFound(relation.to_string(), column.to_string()))?;
+        let cur_vld = self.now();
+        let key: Vec<_> = key
+            .iter()
+            .zip(handle.metadata.keys.iter())
+            .map(|(v, col)| col.typing.coerce(v.clone(), cur_vld))
+            .try_collect()?;
+        Ok(handle
+            .get(&tx, &key)?
+            .map(|mut tuple| tuple.swap_remove(col_idx)))
+    }
+    /// Read many rows of a stored relation by their full keys in one transaction.
+    /// The rows are looked up in key order, but returned in the order of `keys`,
+    /// with `None` for keys under which no row is stored. Each row holds the key
+    /// columns followed by the non-key columns, as in [Self::export_relations].
+    pub fn get_rows(
+        &'s self,
+        relation: &str,
+        keys: &[Vec<DataValue>],
+    ) -> Result<Vec<Option<Tuple>>> {
+        let tx = self.transact()?;
+        let handle = tx.get_relation(relation, false)?;
+        if handle.access_level < AccessLevel::ReadOnly {
+            bail!(InsufficientAccessLevel(
+                handle.name.to_string(),
+                "row lookup".to_string(),
+                handle.access_level
+            ));
+        }
+        let cur_vld = self.now();
+        let keys: Vec<Vec<_>> = keys
+            .iter()
+            .map(|key| -> Result<Vec<DataValue>> {
+                ensure!(
+                    key.len() == handle.metadata.keys.len(),
+                    KeyArityMismatch(relation.to_string(), handle.metadata.keys.len(), key.len())
+                );
+                key.iter()
+                    .zip(handle.metadata.keys.iter())
+                    .map(|(v, col)| col.typing.coerce(v.clone(), cur_vld))
+                    .try_collect()
+            })
+            .try_collect()?;
+        let mut ret = vec![None; keys.len()];
+        for i in (0..keys.len()).sorted_by(|a, b| keys[*a].cmp(&keys[*b])) {
+            ret[i] = handle.get(&tx, &keys[i])?;
+        }
+        Ok(ret)
+    }
+    /// Read through the stored data of `relations` once, so that storage engines
+    /// with a block cache, such as RocksDB, serve the first queries over them
+    /// from memory. Returns the number of key-value pairs read.
+    pub fn warmup<I, T>(&'s self, relations: I) -> Result<usize>
+    where
+        T: AsRef<str>,
+        I: Iterator<Item = T>,
+    {
+        let tx = self.transact()?;
+        let mut count = 0;
+        for rel in relations {
+            let handle = tx.get_relation(rel.as_ref(), false)?;
+            if handle.access_level < AccessLevel::ReadOnly {
+                bail!(InsufficientAccessLevel(
+                    handle.name.to_string(),
+                    "warmup".to_string(),
+                    handle.access_level
+                ));
+            }
+            let start = Tuple::default().encode_as_key(handle.id);
+            let end = Tuple::default().encode_as_key(handle.id.next());
+            for data in tx.store_tx.range_scan(&start, &end) {
+                data?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+    /// Count the stored versions, assertions and retractions alike, of a row of a
+    /// relation whose last key column has type `Validity`. `key` holds the values
+    /// of the other key columns. Returns 0 if the row was never written.
+    pub fn version_count(&'s self, relation: &str, key: &[DataValue]) -> Result<usize> {
+        let tx = self.transact()?;
+        let handle = tx.get_relation(relation, false)?;
+        if handle.access_level < AccessLevel::ReadOnly {
+            bail!(InsufficientAccessLevel(
+                handle.name.to_string(),
+                "version count".to_string(),
+                handle.access_level
+            ));
+        }
+        let is_versioned = handle
+            .metadata
+            .keys
+            .last()
+            .is_some_and(|col| col.typing.coltype == ColType::Validity);
+        ensure!(is_versioned, RelationNotVersioned(relation.to_string()));
+        let n_prefix = handle.metadata.keys.len() - 1;
+        ensure!(
+            key.len() == n_prefix,
+            KeyArityMismatch(relation.to_string(), n_prefix, key.len())
+        );
+        let cur_vld = self.now();
+        let prefix: Vec<_> = key
+            .iter()
+            .zip(handle.metadata.keys.iter())
+            .map(|(v, col)| col.typing.coerce(v.clone(), cur_vld))
+            .try_collect()?;
+        let mut count = 0;
+        for tuple in handle.scan_prefix(&tx, &prefix) {
+            tuple?;
+            count += 1;
+        }
+        Ok(count)
+    }
+    /// Profile a column of a stored relation: returns the `top_n` most common values
+    /// with their number of rows, most common first, ties broken by value.
+    pub fn column_value_histogram(
+        &'s self,
+        relation: &str,
+        column: &str,
+        top_n: usize,
+    ) -> Result<NamedRows> {
+        let tx = self.transact()?;
+        let handle = tx.get_relation(relation, false)?;
+        if handle.access_level < AccessLevel::ReadOnly {
+            bail!(InsufficientAccessLevel(
+                handle.name.to_string(),
+                "histogram".to_string(),
+                handle.access_level
+            ));
+        }
+        let col_idx = handle
+            .metadata
+            .keys
+            .iter()
+            .chain(handle.metadata.non_keys.iter())
+            .position(|col| col.name == column)
+            .ok_or_else(|| ColumnNotFound(relation.to_string(), column.to_string()))?;
+
+        let mut counts: BTreeMap<DataValue, i64> = BTreeMap::new();
+        for tuple in handle.scan_all(&tx) {
+            let mut tuple = tuple?;
+            *counts.entry(tuple.swap_remove(col_idx)).or_default() += 1;
+        }
+        let rows = counts
+            .into_iter()
+            .sorted_by(|(v1, c1), (v2, c2)| c2.cmp(c1).then_with(|| v1.cmp(v2)))
+            .take(top_n)
+            .map(|(v, c)| vec![v, DataValue::from(c)])
+            .collect_vec();
+        Ok(NamedRows::new(
+            vec![column.to_string(), "count".to_string()],
+            rows,
+        ))
+    }
+    /// Create a stored relation from a typed schema. This runs the `:create`
+    /// query that `def` displays as, so the stored metadata is the same.
+    pub fn create_relation(&'s self, def: &RelationDef) -> Result<()> {
+        self.run_script(
+            &def.to_string(),
+            Default::default(),
+            ScriptMutability::Mutable,
+        )?;
+        Ok(())
+    }
     /// Import relations. The argument `data` accepts data in the shape of
     /// what was returned by [Self::export_relations].
     /// The target stored relations must already exist in the database.
@@ -457,16 +957,31 @@ impl<'s, S: Storage<'s>> Db<S> {
     /// Note that triggers and callbacks are _not_ run for the relations, if any exists.
     /// If you need to activate triggers or callbacks, use queries with parameters.
     pub fn import_relations(&'s self, data: BTreeMap<String, NamedRows>) -> Result<()> {
+        self.import_relations_with_mode(data, ImportMode::Put)
+    }
+    /// Import relations, applying all rows according to `mode`.
+    /// As with [Self::import_relations], relation names prefixed with `-`
+    /// have their rows removed regardless of `mode`.
+    pub fn import_relations_with_mode(
+        &'s self,
+        data: BTreeMap<String, NamedRows>,
+        mode: ImportMode,
+    ) -> Result<()> {
         #[derive(Debug, Diagnostic, Error)]
         #[error("cannot import data for relation '{0}': {1}")]
         #[diagnostic(code(import::bad_data))]
         struct BadDataForRelation(String, JsonValue);
 
+        #[derive(Debug, Diagnostic, Error)]
+        #[error("cannot insert into relation '{0}': key {1:?} already exists")]
+        #[diagnostic(code(import::key_exists))]
+        struct ImportKeyExists(String, Vec<DataValue>);
+
         let rel_names = data.keys().map(SmartString::from).collect_vec();
         let locks = self.obtain_relation_locks(rel_names.iter());
         let _guards = locks.iter().map(|l| l.read().unwrap()).collect_vec();
 
-        let cur_vld = current_validity();
+        let cur_vld = self.now();
 
         let mut tx = self.transact_write()?;
 
@@ -474,7 +989,7 @@ impl<'s, S: Storage<'s>> Db<S> {
             let is_delete;
             let relation: &str = match relation_op.strip_prefix('-') {
                 None => {
-                    is_delete = false;
+                    is_delete = mode == ImportMode::Rm;
                     &relation_op
                 }
                 Some(s) => {
@@ -550,6 +1065,12 @@ impl<'s, S: Storage<'s>> Db<S> {
                     })
                     .try_collect()?;
                 let k_store = handle.encode_key_for_store(&keys, Default::default())?;
+                if mode == ImportMode::Insert
+                    && !is_delete
+                    && tx.store_tx.exists(&k_store, false)?
+                {
+                    bail!(ImportKeyExists(relation.to_string(), keys))
+                }
                 if has_indices {
                     if let Some(existing) = tx.store_tx.get(&k_store, false)? {
                         let mut old = keys.clone();
@@ -595,6 +1116,284 @@ impl<'s, S: Storage<'s>> Db<S> {
         tx.commit_tx()?;
         Ok(())
     }
+    /// Replicate stored relations of this database into `target`, which may use
+    /// another storage engine. The stores are scanned side by side in key order:
+    /// rows that `target` lacks or stores with different values are written, and
+    /// rows only present in `target` are removed, both with [Self::import_relations].
+    /// For relations whose last key column has type `Validity`, only versions at or
+    /// after `since` are compared, so replicating periodically with the time of the
+    /// previous run only ships the new history. The relations must already exist in
+    /// `target` with the same columns. Returns the number of rows written or removed.
+    pub fn replicate_to<'t, S2, I, T>(
+        &'s self,
+        target: &'t Db<S2>,
+        relations: I,
+        since: Option<ValidityTs>,
+    ) -> Result<usize>
+    where
+        S2: Storage<'t>,
+        T: AsRef<str>,
+        I: Iterator<Item = T>,
+    {
+        #[derive(Debug, Error, Diagnostic)]
+        #[error("Relation '{0}' has different columns in the target database")]
+        #[diagnostic(code(eval::replica_schema_mismatch))]
+        struct ReplicaSchemaMismatch(String);
+
+        use std::cmp::Ordering;
+
+        let src_tx = self.transact()?;
+        let dst_tx = target.transact()?;
+        let mut changes = BTreeMap::new();
+        let mut changed = 0;
+        for rel in relations {
+            let rel = rel.as_ref();
+            let handle = src_tx.get_relation(rel, false)?;
+            if handle.access_level < AccessLevel::ReadOnly {
+                bail!(InsufficientAccessLevel(
+                    handle.name.to_string(),
+                    "replication".to_string(),
+                    handle.access_level
+                ));
+            }
+            let dst_handle = dst_tx.get_relation(rel, false)?;
+            let same_columns = handle
+                .metadata
+                .keys
+                .iter()
+                .chain(handle.metadata.non_keys.iter())
+                .map(|col| &col.name)
+                .eq(dst_handle
+                    .metadata
+                    .keys
+                    .iter()
+                    .chain(dst_handle.metadata.non_keys.iter())
+                    .map(|col| &col.name));
+            ensure!(
+                same_columns && handle.metadata.keys.len() == dst_handle.metadata.keys.len(),
+                ReplicaSchemaMismatch(rel.to_string())
+            );
+
+            let n_keys = handle.metadata.keys.len();
+            let is_versioned = handle
+                .metadata
+                .keys
+                .last()
+                .is_some_and(|col| col.typing.coltype == ColType::Validity);
+            let in_window = |tuple: &Tuple| match (&tuple[n_keys - 1], since) {
+                (DataValue::Validity(vld), Some(since)) if is_versioned => {
+                    !since.is_after(&vld.timestamp)
+                }
+                _ => true,
+            };
+
+            let mut puts = vec![];
+            let mut removals = vec![];
+            let mut ours = handle.scan_all(&src_tx);
+            let mut theirs = dst_handle.scan_all(&dst_tx);
+            let mut cur_ours = ours.next().transpose()?;
+            let mut cur_theirs = theirs.next().transpose()?;
+            loop {
+                match (cur_ours.take(), cur_theirs.take()) {
+                    (None, None) => break,
+                    (Some(a), None) => {
+                        if in_window(&a) {
+                            puts.push(a);
+                        }
+                        cur_ours = ours.next().transpose()?;
+                    }
+                    (None, Some(mut b)) => {
+                        if in_window(&b) {
+                            b.truncate(n_keys);
+                            removals.push(b);
+                        }
+                        cur_theirs = theirs.next().transpose()?;
+                    }
+                    (Some(a), Some(mut b)) => match a[..n_keys].cmp(&b[..n_keys]) {
+                        Ordering::Less => {
+                            if in_window(&a) {
+                                puts.push(a);
+                            }
+                            cur_ours = ours.next().transpose()?;
+                            cur_theirs = Some(b);
+                        }
+                        Ordering::Greater => {
+                            if in_window(&b) {
+                                b.truncate(n_keys);
+                                removals.push(b);
+                            }
+                            cur_ours = Some(a);
+                            cur_theirs = theirs.next().transpose()?;
+                        }
+                        Ordering::Equal => {
+                            if a != b && in_window(&a) {
+                                puts.push(a);
+                            }
+                            cur_ours = ours.next().transpose()?;
+                            cur_theirs = theirs.next().transpose()?;
+                        }
+                    },
+                }
+            }
+
+            let key_headers = handle
+                .metadata
+                .keys
+                .iter()
+                .map(|col| col.name.to_string())
+                .collect_vec();
+            let mut headers = key_headers.clone();
+            headers.extend(
+                handle
+                    .metadata
+                    .non_keys
+                    .iter()
+                    .map(|col| col.name.to_string()),
+            );
+            changed += puts.len() + removals.len();
+            if !puts.is_empty() {
+                changes.insert(rel.to_string(), NamedRows::new(headers, puts));
+            }
+            if !removals.is_empty() {
+                changes.insert(format!("-{rel}"), NamedRows::new(key_headers, removals));
+            }
+        }
+        drop(src_tx);
+        drop(dst_tx);
+        target.import_relations(changes)?;
+        Ok(changed)
+    }
+    /// Retract every row of `relation`, whose last key column must have type
+    /// `Validity`, if its current version is an assertion made before `older_than`.
+    /// The retractions are stamped with the current validity, so earlier history
+    /// stays visible to time-travel queries. Any associated indices are updated, but
+    /// as with [Self::import_relations], triggers and callbacks are _not_ run.
+    /// Returns the number of rows retracted.
+    pub fn expire(&'s self, relation: &str, older_than: ValidityTs) -> Result<usize> {
+        let mut tx = self.transact_write()?;
+        let handle = tx.get_relation(relation, false)?;
+        if handle.access_level < AccessLevel::Protected {
+            bail!(InsufficientAccessLevel(
+                handle.name.to_string(),
+                "expiry".to_string(),
+                handle.access_level
+            ));
+        }
+        let is_versioned = handle
+            .metadata
+            .keys
+            .last()
+            .is_some_and(|col| col.typing.coltype == ColType::Validity);
+        ensure!(is_versioned, RelationNotVersioned(relation.to_string()));
+        let locks = self.obtain_relation_locks(iter::once(&handle.name));
+        let _guard = locks[0].read().unwrap();
+
+        let n_keys = handle.metadata.keys.len();
+        let retraction = DataValue::Validity(Validity {
+            timestamp: self.now(),
+            is_assert: Reverse(false),
+        });
+        let mut rows = vec![];
+        let mut last_prefix: Option<Tuple> = None;
+        // versions of the same key are scanned from the newest to the oldest
+        for tuple in handle.scan_all(&tx) {
+            let mut tuple = tuple?;
+            let prefix = &tuple[..n_keys - 1];
+            if last_prefix.as_deref() == Some(prefix) {
+                continue;
+            }
+            last_prefix = Some(prefix.to_vec());
+            if let DataValue::Validity(vld) = &tuple[n_keys - 1] {
+                if vld.is_assert.0 && older_than.is_after(&vld.timestamp) {
+                    tuple[n_keys - 1] = retraction.clone();
+                    rows.push(tuple);
+                }
+            }
+        }
+
+        for row in &rows {
+            let k_store = handle.encode_key_for_store(&row[..n_keys], Default::default())?;
+            let v_store = handle.encode_val_only_for_store(&row[n_keys..], Default::default())?;
+            tx.store_tx.put(&k_store, &v_store)?;
+            for (idx_rel, extractor) in handle.indices.values() {
+                let idx_tup = extractor.iter().map(|i| row[*i].clone()).collect_vec();
+                let encoded = idx_rel.encode_key_for_store(&idx_tup, Default::default())?;
+                tx.store_tx.put(&encoded, &[])?;
+            }
+        }
+        tx.commit_tx()?;
+        Ok(rows.len())
+    }
+    /// Physically remove superseded versions from relations whose last key column
+    /// has type `Validity`. For every key, the newest version valid at `before` is
+    /// kept together with all later versions, so queries at or after `before` are
+    /// unaffected, while earlier history is lost. Relations with an access level
+    /// below normal are skipped. Returns the number of versions removed.
+    pub fn gc(&'s self, before: ValidityTs) -> Result<usize> {
+        let mut tx = self.transact_write()?;
+        let lower = vec![DataValue::from("")].encode_as_key(RelationId::SYSTEM);
+        let upper =
+            vec![DataValue::from(String::from(LARGEST_UTF_CHAR))].encode_as_key(RelationId::SYSTEM);
+        let mut handles = vec![];
+        for kv_res in tx.store_tx.range_scan(&lower, &upper) {
+            let (k_slice, v_slice) = kv_res?;
+            if upper <= k_slice {
+                break;
+            }
+            let handle = RelationHandle::decode(&v_slice)?;
+            let is_versioned = handle
+                .metadata
+                .keys
+                .last()
+                .is_some_and(|col| col.typing.coltype == ColType::Validity);
+            // index entries are removed together with the rows they point to
+            if is_versioned
+                && !handle.name.contains(':')
+                && handle.access_level == AccessLevel::Normal
+            {
+                handles.push(handle);
+            }
+        }
+        let rel_names = handles.iter().map(|h| h.name.clone()).collect_vec();
+        let locks = self.obtain_relation_locks(rel_names.iter());
+        let _guards = locks.iter().map(|l| l.read().unwrap()).collect_vec();
+
+        let mut removed = 0;
+        for handle in handles {
+            let n_keys = handle.metadata.keys.len();
+            let mut to_remove = vec![];
+            let mut kept_prefix: Option<Tuple> = None;
+            // versions of the same key are scanned from the newest to the oldest
+            for tuple in handle.scan_all(&tx) {
+                let tuple = tuple?;
+                let prefix = &tuple[..n_keys - 1];
+                if kept_prefix.as_deref() == Some(prefix) {
+                    to_remove.push(tuple);
+                } else if let DataValue::Validity(vld) = &tuple[n_keys - 1] {
+                    if !vld.timestamp.is_after(&before) {
+                        kept_prefix = Some(prefix.to_vec());
+                    }
+                }
+            }
+            removed += to_remove.len();
+            for tuple in to_remove {
+                for (idx_rel, extractor) in handle.indices.values() {
+                    let idx_tup = extractor.iter().map(|i| tuple[*i].clone()).collect_vec();
+                    let encoded = idx_rel.encode_key_for_store(&idx_tup, Default::default())?;
+                    tx.store_tx.del(&encoded)?;
+                }
+                let k_store = handle.encode_key_for_store(&tuple[..n_keys], Default::default())?;
+                tx.store_tx.del(&k_store)?;
+            }
+        }
+        tx.commit_tx()?;
+        Ok(removed)
+    }
+    /// Flush all committed writes to durable storage, so that they survive
+    /// an abrupt process exit. A no-op for engines that persist on commit.
+    pub fn flush(&'s self) -> Result<()> {
+        self.db.flush()
+    }
     /// Backup the running database into an Sqlite file
     #[allow(unused_variables)]
     pub fn backup_db(&'s self, out_file: impl AsRef<Path>) -> Result<()> {
@@ -712,6 +1511,43 @@ impl<'s, S: Storage<'s>> Db<S> {
             dst_tx.commit_tx()
         }
     }
+    /// Replace the clock giving the current validity of queries and writes,
+    /// e.g. with a fixed time so that tests of time travel are reproducible.
+    /// The `now()` function in scripts still reads the system clock.
+    pub fn set_clock<F>(&self, clock: F)
+    where
+        F: Fn() -> ValidityTs + Send + Sync + 'static,
+    {
+        *self.clock.write().unwrap() = Box::new(clock);
+    }
+
+    /// Go back to reading the current validity from the system clock.
+    pub fn reset_clock(&self) {
+        *self.clock.write().unwrap() = Box::new(current_validity);
+    }
+
+    /// Call `hook` after every `every` tuples produced by the body of a rule,
+    /// counted separately for each rule body, and after every `every` tuples
+    /// read from stored relations, counted for each transaction. Long-running
+    /// queries can then cooperate with an async scheduler, e.g. by checking in
+    /// with the runtime, even when a selective filter discards most of a long scan.
+    /// An `every` of 0 is treated as 1.
+    pub fn set_yield_hook<F>(&self, every: usize, hook: F)
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        *self.yield_hook.write().unwrap() = Some(YieldHook::new(every, Arc::new(hook)));
+    }
+
+    /// Stop calling the hook set by [`Db::set_yield_hook`].
+    pub fn clear_yield_hook(&self) {
+        *self.yield_hook.write().unwrap() = None;
+    }
+
+    pub(crate) fn now(&self) -> ValidityTs {
+        (self.clock.read().unwrap())()
+    }
+
     /// Register a custom fixed rule implementation.
     pub fn register_fixed_rule<R>(&self, name: String, rule_impl: R) -> Result<()>
     where
@@ -832,6 +1668,14 @@ impl<'s, S: Storage<'s>> Db<S> {
             relation_store_id: self.relation_store_id.clone(),
             temp_store_id: Default::default(),
             tokenizers: self.tokenizers.clone(),
+            profile: None,
+            aggr_limits: Default::default(),
+            yield_hook: self
+                .yield_hook
+                .read()
+                .unwrap()
+                .as_ref()
+                .map(YieldHook::for_tx),
         };
         Ok(ret)
     }
@@ -842,6 +1686,14 @@ impl<'s, S: Storage<'s>> Db<S> {
             relation_store_id: self.relation_store_id.clone(),
             temp_store_id: Default::default(),
             tokenizers: self.tokenizers.clone(),
+            profile: None,
+            aggr_limits: Default::default(),
+            yield_hook: self
+                .yield_hook
+                .read()
+                .unwrap()
+                .as_ref()
+                .map(YieldHook::for_tx),
         };
         Ok(ret)
     }
@@ -940,8 +1792,13 @@ impl<'s, S: Storage<'s>> Db<S> {
 
         Ok(res)
     }
-    fn explain_compiled(&self, strata: &[CompiledProgram]) -> Result<NamedRows> {
+    fn explain_compiled(
+        &self,
+        strata: &[CompiledProgram],
+        profile: Option<&BTreeMap<usize, NodeProfile>>,
+    ) -> Result<(NamedRows, Vec<NodeTrace>)> {
         let mut ret: Vec<JsonValue> = vec![];
+        let mut traces = vec![];
         const STRATUM: &str = "stratum";
         const ATOM_IDX: &str = "atom_idx";
         const OP: &str = "op";
@@ -951,8 +1808,10 @@ impl<'s, S: Storage<'s>> Db<S> {
         const OUT_BINDINGS: &str = "out_relation";
         const JOINS_ON: &str = "joins_on";
         const FILTERS: &str = "filters/expr";
+        const ACTUAL_ROWS: &str = "actual_rows";
+        const MS: &str = "ms";
 
-        let headers = vec![
+        let mut headers = vec![
             STRATUM.to_string(),
             RULE_IDX.to_string(),
             RULE_NAME.to_string(),
@@ -963,6 +1822,18 @@ impl<'s, S: Storage<'s>> Db<S> {
             FILTERS.to_string(),
             OUT_BINDINGS.to_string(),
         ];
+        if profile.is_some() {
+            headers.push(ACTUAL_ROWS.to_string());
+            headers.push(MS.to_string());
+        }
+        let stats_of = |rel: &RelAlgebra| match profile.and_then(|p| p.get(&rel.profile_key())) {
+            None => (json!(null), json!(null)),
+            Some(node) => (json!(node.rows), json!(node.elapsed.as_secs_f64() * 1000.)),
+        };
+        let samples_of = |rel: &RelAlgebra| match profile.and_then(|p| p.get(&rel.profile_key())) {
+            Some(node) if !node.samples.is_empty() => Some(node.samples.clone()),
+            _ => None,
+        };
 
         for (stratum, p) in strata.iter().enumerate() {
             let mut clause_idx = -1;
@@ -985,13 +1856,26 @@ impl<'s, S: Storage<'s>> Db<S> {
                                 }
                             }
 
+                            let (actual_rows, ms) = stats_of(relation);
+                            if let Some(samples) = samples_of(relation) {
+                                traces.push(NodeTrace {
+                                    stratum,
+                                    rule: rule_name.to_string(),
+                                    rule_idx: clause_idx as usize,
+                                    atom_idx: idx,
+                                    op: atom_type.to_string(),
+                                    samples,
+                                });
+                            }
                             ret_for_relation.push(json!({
                                 STRATUM: stratum,
                                 ATOM_IDX: idx,
                                 OP: atom_type,
                                 RULE_IDX: clause_idx,
                                 RULE_NAME: rule_name.to_string(),
-                                OUT_BINDINGS: relation.bindings_after_eliminate().into_iter().map(|v| v.to_string()).collect_vec()
+                                OUT_BINDINGS: relation.bindings_after_eliminate().into_iter().map(|v| v.to_string()).collect_vec(),
+                                ACTUAL_ROWS: actual_rows,
+                                MS: ms,
                             }));
                             idx += 1;
 
@@ -1047,6 +1931,31 @@ impl<'s, S: Storage<'s>> Db<S> {
                                         rel_stack.push(right);
                                         (t, json!(null), json!(joiner.as_map()), json!(null))
                                     }
+                                    RelAlgebra::ThetaJoin(inner) => {
+                                        let t = inner.join_type();
+                                        let ThetaJoin {
+                                            inner:
+                                                InnerJoin {
+                                                    left,
+                                                    right,
+                                                    joiner,
+                                                    ..
+                                                },
+                                            theta,
+                                            ..
+                                        } = inner.as_ref();
+                                        rel_stack.push(left);
+                                        rel_stack.push(right);
+                                        (
+                                            t,
+                                            json!(null),
+                                            json!(joiner.as_map()),
+                                            json!(theta
+                                                .iter()
+                                                .map(|f| f.to_string())
+                                                .collect_vec()),
+                                        )
+                                    }
                                     RelAlgebra::NegJoin(inner) => {
                                         let t = inner.join_type();
                                         let NegJoin {
@@ -1124,6 +2033,17 @@ impl<'s, S: Storage<'s>> Db<S> {
                                             .collect_vec()),
                                     ),
                                 };
+                                let (actual_rows, ms) = stats_of(rel);
+                                if let Some(samples) = samples_of(rel) {
+                                    traces.push(NodeTrace {
+                                        stratum,
+                                        rule: rule_name.to_string(),
+                                        rule_idx: clause_idx as usize,
+                                        atom_idx: idx,
+                                        op: atom_type.to_string(),
+                                        samples,
+                                    });
+                                }
                                 ret_for_relation.push(json!({
                                     STRATUM: stratum,
                                     ATOM_IDX: idx,
@@ -1134,6 +2054,8 @@ impl<'s, S: Storage<'s>> Db<S> {
                                     OUT_BINDINGS: rel.bindings_after_eliminate().into_iter().map(|v| v.to_string()).collect_vec(),
                                     JOINS_ON: joins_on,
                                     FILTERS: filters,
+                                    ACTUAL_ROWS: actual_rows,
+                                    MS: ms,
                                 }));
                                 idx += 1;
                             }
@@ -1162,7 +2084,7 @@ impl<'s, S: Storage<'s>> Db<S> {
             })
             .collect_vec();
 
-        Ok(NamedRows::new(headers, rows))
+        Ok((NamedRows::new(headers, rows), traces))
     }
     pub(crate) fn run_sys_op_with_tx(
         &'s self,
@@ -1177,7 +2099,8 @@ impl<'s, S: Storage<'s>> Db<S> {
                 let (stratified_program, _) = normalized_program.into_stratified_program()?;
                 let program = stratified_program.magic_sets_rewrite(tx)?;
                 let compiled = tx.stratified_magic_compile(program)?;
-                self.explain_compiled(&compiled)
+                let (plan, _) = self.explain_compiled(&compiled, None)?;
+                Ok(plan)
             }
             SysOp::Compact => {
                 if read_only {
@@ -1474,6 +2397,7 @@ impl<'s, S: Storage<'s>> Db<S> {
         // query compilation
         let entry_head_or_default = input_program.get_entry_out_head_or_default()?;
         let (normalized_program, out_opts) = input_program.into_normalized_program(tx)?;
+        tx.aggr_limits = out_opts.aggr_limits();
         let (stratified_program, store_lifetimes) = normalized_program.into_stratified_program()?;
         let program = stratified_program.magic_sets_rewrite(tx)?;
         let compiled = tx.stratified_magic_compile(program)?;
@@ -1523,30 +2447,7 @@ impl<'s, S: Storage<'s>> Db<S> {
         )?;
 
         // deal with assertions
-        if let Some(assertion) = &out_opts.assertion {
-            match assertion {
-                QueryAssertion::AssertNone(span) => {
-                    if let Some(tuple) = result_store.all_iter().next() {
-                        #[derive(Debug, Error, Diagnostic)]
-                        #[error(
-                            "The query is asserted to return no result, but a tuple {0:?} is found"
-                        )]
-                        #[diagnostic(code(eval::assert_none_failure))]
-                        struct AssertNoneFailure(Tuple, #[label] SourceSpan);
-                        bail!(AssertNoneFailure(tuple.into_tuple(), *span))
-                    }
-                }
-                QueryAssertion::AssertSome(span) => {
-                    if result_store.all_iter().next().is_none() {
-                        #[derive(Debug, Error, Diagnostic)]
-                        #[error("The query is asserted to return some results, but returned none")]
-                        #[diagnostic(code(eval::assert_some_failure))]
-                        struct AssertSomeFailure(#[label] SourceSpan);
-                        bail!(AssertSomeFailure(*span))
-                    }
-                }
-            }
-        }
+        check_assertion(&out_opts, &result_store)?;
 
         if !out_opts.sorters.is_empty() {
             // sort outputs if required
@@ -1761,6 +2662,7 @@ impl<'s, S: Storage<'s>> Db<S> {
                 json!(idx),
                 json!(col.typing.to_string()),
                 json!(col.default_gen.is_some()),
+                json!(col.description),
             ]);
             idx += 1;
         }
@@ -1771,6 +2673,7 @@ impl<'s, S: Storage<'s>> Db<S> {
                 json!(idx),
                 json!(col.typing.to_string()),
                 json!(col.default_gen.is_some()),
+                json!(col.description),
             ]);
             idx += 1;
         }
@@ -1785,6 +2688,7 @@ impl<'s, S: Storage<'s>> Db<S> {
                 "index".to_string(),
                 "type".to_string(),
                 "has_default".to_string(),
+                "description".to_string(),
             ],
             rows,
         ))
@@ -1923,6 +2827,34 @@ impl Poison {
     }
 }
 
+fn check_assertion(out_opts: &QueryOutOptions, result_store: &EpochStore) -> Result<()> {
+    if let Some(assertion) = &out_opts.assertion {
+        match assertion {
+            QueryAssertion::AssertNone(span) => {
+                if let Some(tuple) = result_store.all_iter().next() {
+                    #[derive(Debug, Error, Diagnostic)]
+                    #[error(
+                        "The query is asserted to return no result, but a tuple {0:?} is found"
+                    )]
+                    #[diagnostic(code(eval::assert_none_failure))]
+                    struct AssertNoneFailure(Tuple, #[label] SourceSpan);
+                    bail!(AssertNoneFailure(tuple.into_tuple(), *span))
+                }
+            }
+            QueryAssertion::AssertSome(span) => {
+                if result_store.all_iter().next().is_none() {
+                    #[derive(Debug, Error, Diagnostic)]
+                    #[error("The query is asserted to return some results, but returned none")]
+                    #[diagnostic(code(eval::assert_some_failure))]
+                    struct AssertSomeFailure(#[label] SourceSpan);
+                    bail!(AssertSomeFailure(*span))
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
 pub(crate) fn seconds_since_the_epoch() -> Result<f64> {
     #[cfg(not(target_arch = "wasm32"))]
     let now = SystemTime::now();
@@ -368,6 +368,7 @@ impl SessionTx<'_> {
                     nullable: true,
                 },
                 default_gen: None,
+                description: Default::default(),
             })
             .collect_vec();
 
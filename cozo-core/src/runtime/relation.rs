@@ -29,6 +29,7 @@ use crate::parse::expr::build_expr;
 use crate::parse::sys::{FtsIndexConfig, HnswIndexConfig, MinHashLshConfig};
 use crate::parse::{CozoScriptParser, Rule, SourceSpan};
 use crate::query::compile::IndexPositionUse;
+use crate::query::ra::YieldHook;
 use crate::runtime::hnsw::HnswIndexManifest;
 use crate::runtime::minhash_lsh::{HashPermutations, LshParams, MinHashLshIndexManifest, Weights};
 use crate::runtime::transact::SessionTx;
@@ -360,11 +361,12 @@ impl RelationHandle {
     ) -> impl Iterator<Item = Result<Tuple>> + 'a {
         let lower = Tuple::default().encode_as_key(self.id);
         let upper = Tuple::default().encode_as_key(self.id.next());
-        if self.is_temp {
+        let it = if self.is_temp {
             tx.temp_store_tx.range_scan_tuple(&lower, &upper)
         } else {
             tx.store_tx.range_scan_tuple(&lower, &upper)
-        }
+        };
+        count_scanned(tx.yield_hook.as_ref(), it)
     }
 
     pub(crate) fn skip_scan_all<'a>(
@@ -374,12 +376,13 @@ impl RelationHandle {
     ) -> impl Iterator<Item = Result<Tuple>> + 'a {
         let lower = Tuple::default().encode_as_key(self.id);
         let upper = Tuple::default().encode_as_key(self.id.next());
-        if self.is_temp {
+        let it = if self.is_temp {
             tx.temp_store_tx
                 .range_skip_scan_tuple(&lower, &upper, valid_at)
         } else {
             tx.store_tx.range_skip_scan_tuple(&lower, &upper, valid_at)
-        }
+        };
+        count_scanned(tx.yield_hook.as_ref(), it)
     }
 
     pub(crate) fn get(&self, tx: &SessionTx<'_>, key: &[DataValue]) -> Result<Option<Tuple>> {
@@ -436,13 +439,14 @@ impl RelationHandle {
         upper.push(DataValue::Bot);
         let prefix_encoded = lower.encode_as_key(self.id);
         let upper_encoded = upper.encode_as_key(self.id);
-        if self.is_temp {
+        let it = if self.is_temp {
             tx.temp_store_tx
                 .range_scan_tuple(&prefix_encoded, &upper_encoded)
         } else {
             tx.store_tx
                 .range_scan_tuple(&prefix_encoded, &upper_encoded)
-        }
+        };
+        count_scanned(tx.yield_hook.as_ref(), it)
     }
 
     pub(crate) fn skip_scan_prefix<'a>(
@@ -457,13 +461,14 @@ impl RelationHandle {
         upper.push(DataValue::Bot);
         let prefix_encoded = lower.encode_as_key(self.id);
         let upper_encoded = upper.encode_as_key(self.id);
-        if self.is_temp {
+        let it = if self.is_temp {
             tx.temp_store_tx
                 .range_skip_scan_tuple(&prefix_encoded, &upper_encoded, valid_at)
         } else {
             tx.store_tx
                 .range_skip_scan_tuple(&prefix_encoded, &upper_encoded, valid_at)
-        }
+        };
+        count_scanned(tx.yield_hook.as_ref(), it)
     }
 
     pub(crate) fn scan_bounded_prefix<'a>(
@@ -475,17 +480,20 @@ impl RelationHandle {
     ) -> impl Iterator<Item = Result<Tuple>> + 'a {
         let mut lower_t = prefix.to_vec();
         lower_t.extend_from_slice(lower);
+        lower_t.truncate(self.metadata.keys.len());
         let mut upper_t = prefix.to_vec();
         upper_t.extend_from_slice(upper);
+        upper_t.truncate(self.metadata.keys.len());
         upper_t.push(DataValue::Bot);
         let lower_encoded = lower_t.encode_as_key(self.id);
         let upper_encoded = upper_t.encode_as_key(self.id);
-        if self.is_temp {
+        let it = if self.is_temp {
             tx.temp_store_tx
                 .range_scan_tuple(&lower_encoded, &upper_encoded)
         } else {
             tx.store_tx.range_scan_tuple(&lower_encoded, &upper_encoded)
-        }
+        };
+        count_scanned(tx.yield_hook.as_ref(), it)
     }
     pub(crate) fn skip_scan_bounded_prefix<'a>(
         &self,
@@ -497,21 +505,36 @@ impl RelationHandle {
     ) -> impl Iterator<Item = Result<Tuple>> + 'a {
         let mut lower_t = prefix.clone();
         lower_t.extend_from_slice(lower);
+        lower_t.truncate(self.metadata.keys.len());
         let mut upper_t = prefix.clone();
         upper_t.extend_from_slice(upper);
+        upper_t.truncate(self.metadata.keys.len());
         upper_t.push(DataValue::Bot);
         let lower_encoded = lower_t.encode_as_key(self.id);
         let upper_encoded = upper_t.encode_as_key(self.id);
-        if self.is_temp {
+        let it = if self.is_temp {
             tx.temp_store_tx
                 .range_skip_scan_tuple(&lower_encoded, &upper_encoded, valid_at)
         } else {
             tx.store_tx
                 .range_skip_scan_tuple(&lower_encoded, &upper_encoded, valid_at)
-        }
+        };
+        count_scanned(tx.yield_hook.as_ref(), it)
     }
 }
 
+/// Report each tuple read from storage to the yield hook, if any
+fn count_scanned<'a>(
+    hook: Option<&'a YieldHook>,
+    it: impl Iterator<Item = Result<Tuple>> + 'a,
+) -> impl Iterator<Item = Result<Tuple>> + 'a {
+    it.inspect(move |_| {
+        if let Some(hook) = hook {
+            hook.count_scanned();
+        }
+    })
+}
+
 const DEFAULT_SIZE_HINT: usize = 16;
 
 /// Decode tuple from key-value pairs. Used for customizing storage
@@ -756,6 +779,7 @@ impl<'a> SessionTx<'a> {
                 nullable: false,
             },
             default_gen: None,
+            description: Default::default(),
         }];
 
         let mut idx_keys = vec![ColumnDef {
@@ -765,12 +789,14 @@ impl<'a> SessionTx<'a> {
                 nullable: false,
             },
             default_gen: None,
+            description: Default::default(),
         }];
         for k in rel_handle.metadata.keys.iter() {
             idx_keys.push(ColumnDef {
                 name: format!("src_{}", k.name).into(),
                 typing: k.typing.clone(),
                 default_gen: None,
+                description: Default::default(),
             });
         }
         let idx_vals = vec![];
@@ -886,6 +912,7 @@ impl<'a> SessionTx<'a> {
                 nullable: false,
             },
             default_gen: None,
+            description: Default::default(),
         }];
 
         for k in rel_handle.metadata.keys.iter() {
@@ -893,6 +920,7 @@ impl<'a> SessionTx<'a> {
                 name: format!("src_{}", k.name).into(),
                 typing: k.typing.clone(),
                 default_gen: None,
+                description: Default::default(),
             });
         }
 
@@ -912,16 +940,19 @@ impl<'a> SessionTx<'a> {
                 name: SmartString::from("offset_from"),
                 typing: col_type.clone(),
                 default_gen: None,
+                description: Default::default(),
             },
             ColumnDef {
                 name: SmartString::from("offset_to"),
                 typing: col_type.clone(),
                 default_gen: None,
+                description: Default::default(),
             },
             ColumnDef {
                 name: SmartString::from("position"),
                 typing: col_type,
                 default_gen: None,
+                description: Default::default(),
             },
             ColumnDef {
                 name: SmartString::from("total_length"),
@@ -930,6 +961,7 @@ impl<'a> SessionTx<'a> {
                     nullable: false,
                 },
                 default_gen: None,
+                description: Default::default(),
             },
         ];
 
@@ -1069,6 +1101,7 @@ impl<'a> SessionTx<'a> {
                 nullable: false,
             },
             default_gen: None,
+            description: Default::default(),
         }];
         // for self-loops, fr and to are identical
         for prefix in ["fr", "to"] {
@@ -1084,6 +1117,7 @@ impl<'a> SessionTx<'a> {
                     nullable: false,
                 },
                 default_gen: None,
+                description: Default::default(),
             });
             idx_keys.push(ColumnDef {
                 name: SmartString::from(format!("{}__sub_idx", prefix)),
@@ -1092,6 +1126,7 @@ impl<'a> SessionTx<'a> {
                     nullable: false,
                 },
                 default_gen: None,
+                description: Default::default(),
             });
         }
 
@@ -1105,6 +1140,7 @@ impl<'a> SessionTx<'a> {
                     nullable: false,
                 },
                 default_gen: None,
+                description: Default::default(),
             },
             // For self-loops, stores a hash of the neighbours, for conflict detection
             ColumnDef {
@@ -1114,6 +1150,7 @@ impl<'a> SessionTx<'a> {
                     nullable: true,
                 },
                 default_gen: None,
+                description: Default::default(),
             },
             ColumnDef {
                 name: SmartString::from("ignore_link"),
@@ -1122,6 +1159,7 @@ impl<'a> SessionTx<'a> {
                     nullable: false,
                 },
                 default_gen: None,
+                description: Default::default(),
             },
         ];
         // create index relation
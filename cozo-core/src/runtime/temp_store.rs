@@ -334,6 +334,7 @@ impl EpochStore {
     pub(crate) fn early_returned_iter(&self) -> impl Iterator<Item = TupleInIter<'_>> {
         self.all_iter().filter(|t| !t.should_skip())
     }
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -8,6 +8,8 @@
  */
 
 use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 
 use itertools::Itertools;
@@ -23,7 +25,11 @@ use crate::fts::{TokenizerCache, TokenizerConfig};
 use crate::parse::SourceSpan;
 use crate::runtime::callback::CallbackOp;
 use crate::runtime::db::Poison;
-use crate::{DbInstance, FixedRule, RegularTempStore, ScriptMutability};
+use crate::runtime::relation::RelationId;
+use crate::{
+    ColumnSpec, DbInstance, FixedRule, ImportMode, NamedRows, RegularTempStore, RelationDef,
+    ScriptMutability, ValidityTs,
+};
 
 #[test]
 fn test_limit_offset() {
@@ -86,7 +92,7 @@ fn test_layers() {
         )
         .unwrap()
         .rows;
-    assert_eq!(res[0][0], DataValue::from(21.))
+    assert_eq!(res[0][0], DataValue::from(21))
 }
 
 #[test]
@@ -159,6 +165,46 @@ fn default_columns() {
     .unwrap();
 }
 
+#[test]
+fn column_descriptions() {
+    let db = DbInstance::default();
+
+    db.run_default(
+        r#"
+            :create airport {
+                iata: String "IATA 3-letter code" =>
+                desc: String,
+                elev: Int 'elevation in feet' default 0
+            }
+            "#,
+    )
+    .unwrap();
+    db.run_default("?[iata, desc] <- [['AUS', 'Austin']] :put airport {iata => desc}")
+        .unwrap();
+
+    let cols = db.run_default("::columns airport").unwrap();
+    let desc_idx = cols
+        .headers
+        .iter()
+        .position(|h| h == "description")
+        .unwrap();
+    let descriptions = cols
+        .rows
+        .iter()
+        .map(|row| row[desc_idx].clone())
+        .collect_vec();
+    assert_eq!(
+        descriptions,
+        vec![
+            DataValue::from("IATA 3-letter code"),
+            DataValue::from(""),
+            DataValue::from("elevation in feet")
+        ]
+    );
+    let res = db.run_default("?[elev] := *airport{elev}").unwrap();
+    assert_eq!(res.into_json()["rows"], json!([[0]]));
+}
+
 #[test]
 fn rm_does_not_need_all_keys() {
     let db = DbInstance::default();
@@ -1237,6 +1283,33 @@ fn returning() {
     }
 }
 
+#[test]
+fn returning_generated_keys() {
+    let db = DbInstance::default();
+    db.run_default(":create airport {id: Uuid default rand_uuid_v4() => code: String}")
+        .unwrap();
+    db.run_default(":create runway {airport: Uuid, n: Int}")
+        .unwrap();
+
+    let res = db
+        .run_default("?[code] <- [['XYZ']] :put airport {code} :returning")
+        .unwrap();
+    let id_idx = res.headers.iter().position(|h| h == "id").unwrap();
+    let id = res.rows[0][id_idx].clone();
+    assert!(matches!(id, DataValue::Uuid(_)));
+
+    db.run_script(
+        "?[airport, n] <- [[$id, 1], [$id, 2]] :put runway {airport, n}",
+        BTreeMap::from([("id".to_string(), id.clone())]),
+        ScriptMutability::Mutable,
+    )
+    .unwrap();
+    let res = db
+        .run_default("?[code, count(n)] := *airport{id, code}, *runway{airport: id, n}")
+        .unwrap();
+    assert_eq!(res.into_json()["rows"], json!([["XYZ", 2]]));
+}
+
 #[test]
 fn parser_corner_case() {
     let db = DbInstance::new("mem", "", "").unwrap();
@@ -1553,4 +1626,1483 @@ fn fts_drop() {
     db.run_default(r#"
         ::fts drop entity:fts_index
     "#).unwrap();
-}
\ No newline at end of file
+}
+
+#[test]
+fn theta_join() {
+    let db = DbInstance::default();
+    db.run_default(
+        r#"
+        ?[grp, dist, name] <- [[1, 10, 'a'], [1, 20, 'b'], [1, 30, 'c'], [2, 5, 'd'], [2, 25, 'e']]
+        :create seg {grp: Int, dist: Int, name: String}
+    "#,
+    )
+    .unwrap();
+
+    let query = r#"
+        ?[a, b] := *seg{grp: 1, dist: d1, name: a}, *seg{grp: 1, dist: d2, name: b}, d1 < d2
+    "#;
+    let res = db.run_default(query).unwrap();
+    assert_eq!(
+        res.into_json()["rows"],
+        json!([["a", "b"], ["a", "c"], ["b", "c"]])
+    );
+
+    let res = db
+        .run_default(
+            r#"
+        ?[a, b] := *seg{grp, dist: d1, name: a}, *seg{grp, dist: d2, name: b}, d1 >= d2 + 10
+    "#,
+        )
+        .unwrap();
+    assert_eq!(
+        res.into_json()["rows"],
+        json!([["b", "a"], ["c", "a"], ["c", "b"], ["e", "d"]])
+    );
+
+    let res = db
+        .run_default(
+            r#"
+        r[name, len] <- [['x', 12], ['y', 27]]
+        ?[name, shorter] := r[name, len], *seg{dist, name: shorter}, dist < len
+    "#,
+        )
+        .unwrap();
+    assert_eq!(
+        res.into_json()["rows"],
+        json!([
+            ["x", "a"],
+            ["x", "d"],
+            ["y", "a"],
+            ["y", "b"],
+            ["y", "d"],
+            ["y", "e"]
+        ])
+    );
+
+    let expl = db
+        .run_default(&format!("::explain {{ {query} }}"))
+        .unwrap()
+        .into_json();
+    let ops = expl["rows"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|row| row.as_array().unwrap()[4].clone())
+        .collect_vec();
+    assert!(ops.contains(&json!("stored_range_join")));
+}
+
+#[test]
+fn optional_atoms() {
+    let db = DbInstance::default();
+    let res = db
+        .run_default(
+            r#"
+        person[name] <- [['alice'], ['bob'], ['carol']]
+        pet[owner, pet] <- [['alice', 'cat'], ['alice', 'dog'], ['carol', 'fish']]
+        ?[name, pet] := person[name], optional pet[name, pet]
+    "#,
+        )
+        .unwrap();
+    assert_eq!(
+        res.into_json()["rows"],
+        json!([
+            ["alice", "cat"],
+            ["alice", "dog"],
+            ["bob", null],
+            ["carol", "fish"]
+        ])
+    );
+
+    let res = db
+        .run_default(
+            r#"
+        person[name] <- [['alice'], ['bob'], ['carol']]
+        pet[owner, pet] <- [['alice', 'cat'], ['alice', 'dog'], ['carol', 'fish']]
+        ?[name, count_non_null(pet)] := person[name], optional pet[name, pet]
+    "#,
+        )
+        .unwrap();
+    assert_eq!(
+        res.into_json()["rows"],
+        json!([["alice", 2], ["bob", 0], ["carol", 1]])
+    );
+
+    assert!(db
+        .run_default(
+            r#"
+        person[name] <- [['alice']]
+        pet[owner, pet] <- [['alice', 'cat']]
+        ?[name, pet] := person[name], (optional pet[name, pet] or pet = 1)
+    "#,
+        )
+        .is_err());
+}
+
+#[test]
+fn import_modes() {
+    let db = DbInstance::default();
+    db.run_default(":create route {fr: String, to: String => dist: Float}")
+        .unwrap();
+    let batch = || {
+        BTreeMap::from([(
+            "route".to_string(),
+            NamedRows::new(
+                vec!["fr".to_string(), "to".to_string(), "dist".to_string()],
+                vec![
+                    vec![
+                        DataValue::from("AUS"),
+                        DataValue::from("DFW"),
+                        DataValue::from(190.),
+                    ],
+                    vec![
+                        DataValue::from("AUS"),
+                        DataValue::from("LHR"),
+                        DataValue::from(4901.),
+                    ],
+                ],
+            ),
+        )])
+    };
+    let count = || {
+        db.run_default("?[count(fr)] := *route{fr}")
+            .unwrap()
+            .into_json()["rows"][0][0]
+            .clone()
+    };
+
+    db.import_relations(batch()).unwrap();
+    assert_eq!(count(), json!(2));
+    db.import_relations_with_mode(batch(), ImportMode::Put)
+        .unwrap();
+    assert_eq!(count(), json!(2));
+    assert!(db
+        .import_relations_with_mode(batch(), ImportMode::Insert)
+        .is_err());
+    db.import_relations_with_mode(batch(), ImportMode::Rm)
+        .unwrap();
+    assert_eq!(count(), json!(0));
+    db.import_relations_with_mode(batch(), ImportMode::Insert)
+        .unwrap();
+    assert_eq!(count(), json!(2));
+}
+
+#[test]
+fn incremental_view_by_triggers() {
+    let db = DbInstance::default();
+    db.run_default(":create route {fr: String, to: String => dist: Float}")
+        .unwrap();
+    db.run_default(":create route_count {fr: String => n: Int}")
+        .unwrap();
+    db.run_default(
+        r#"
+        ::set_triggers route
+
+        on put {
+            affected[fr] := _new[fr, _, _]
+            cnt[fr, count(to)] := affected[fr], *route{fr, to}
+            ?[fr, n] := cnt[fr, n]
+
+            :put route_count {fr => n}
+        }
+        on rm {
+            affected[fr] := _old[fr, _, _]
+            cnt[fr, count(to)] := affected[fr], *route{fr, to}
+            ?[fr, n] := cnt[fr, n]
+            ?[fr, n] := affected[fr], not *route{fr}, n = 0
+
+            :put route_count {fr => n}
+        }
+        "#,
+    )
+    .unwrap();
+    db.run_default(
+        r#"
+        ?[fr, to, dist] <- [['AUS', 'DFW', 190.], ['AUS', 'LHR', 4901.], ['LHR', 'AUS', 4901.]]
+        :put route {fr, to => dist}
+    "#,
+    )
+    .unwrap();
+    let counts = || {
+        db.run_default("?[fr, n] := *route_count{fr, n}")
+            .unwrap()
+            .into_json()["rows"]
+            .clone()
+    };
+    assert_eq!(counts(), json!([["AUS", 2], ["LHR", 1]]));
+
+    // rows for untouched sources are left alone by the triggers,
+    // so tampering with one shows that only affected rows are recomputed
+    db.run_default("?[fr, n] <- [['LHR', 100]] :put route_count {fr => n}")
+        .unwrap();
+    db.run_default("?[fr, to, dist] <- [['AUS', 'JFK', 1521.]] :put route {fr, to => dist}")
+        .unwrap();
+    assert_eq!(counts(), json!([["AUS", 3], ["LHR", 100]]));
+
+    db.run_default("?[fr, to] <- [['LHR', 'AUS']] :rm route {fr, to}")
+        .unwrap();
+    assert_eq!(counts(), json!([["AUS", 3], ["LHR", 0]]));
+}
+
+#[test]
+fn optional_atoms_with_defaults() {
+    let db = DbInstance::default();
+    db.run_default(":create airport {code: String => runways: Int default 1, elev: Int?}")
+        .unwrap();
+    db.run_default(
+        "?[code, runways, elev] <- [['AUS', 2, 542]] :put airport {code => runways, elev}",
+    )
+    .unwrap();
+    let res = db
+        .run_default(
+            r#"
+        ?[code, runways, elev] := code in ['AUS', 'XYZ'], optional *airport{code, runways, elev}
+    "#,
+        )
+        .unwrap();
+    assert_eq!(
+        res.into_json()["rows"],
+        json!([["AUS", 2, 542], ["XYZ", 1, null]])
+    );
+    let res = db
+        .run_default(
+            r#"
+        ?[code, runways, elev] := code in ['AUS', 'XYZ'], optional *airport[code, runways, elev]
+    "#,
+        )
+        .unwrap();
+    assert_eq!(
+        res.into_json()["rows"],
+        json!([["AUS", 2, 542], ["XYZ", 1, null]])
+    );
+}
+
+#[test]
+fn with_local_rules() {
+    let db = DbInstance::default();
+    let res = db
+        .run_default(
+            r#"
+        :with hops[a, b] <- [[1, 2], [2, 3]]
+        :with two_hops[a, c] := hops[a, b], hops[b, c]
+        ?[a, c] := two_hops[a, c]
+    "#,
+        )
+        .unwrap();
+    assert_eq!(res.into_json()["rows"], json!([[1, 3]]));
+    assert!(db.run_default("?[a, c] := two_hops[a, c]").is_err());
+    assert!(db.run_default(":with ?[a] <- [[1]]").is_err());
+}
+
+#[test]
+fn string_escapes() {
+    let db = DbInstance::default();
+    db.run_default(":create airport {code: String => desc: String}")
+        .unwrap();
+    db.run_default(
+        r#"?[code, desc] <- [['BCN', 'Barcelona–El Prat Josep Tarradellas'],
+                             ['CDG', 'Aéroport de Paris-Charles de Gaulle'],
+                             ['ORD', 'Chicago O\'Hare']]
+           :put airport {code => desc}"#,
+    )
+    .unwrap();
+    let res = db
+        .run_default(r#"?[code] := *airport{code, desc: 'Chicago O\'Hare'}"#)
+        .unwrap();
+    assert_eq!(res.into_json()["rows"], json!([["ORD"]]));
+    let res = db
+        .run_default(r#"?[code] := *airport{code, desc: "Aéroport de Paris-Charles de Gaulle"}"#)
+        .unwrap();
+    assert_eq!(res.into_json()["rows"], json!([["CDG"]]));
+    let res = db
+        .run_default(r#"?[s] := s = 'tab\there\nand \ud83d\ude80'"#)
+        .unwrap();
+    assert_eq!(res.into_json()["rows"], json!([["tab\there\nand 🚀"]]));
+    assert!(db.run_default(r#"?[s] := s = '\ud83d'"#).is_err());
+    assert!(db.run_default(r#"?[s] := s = '\ud83dx'"#).is_err());
+}
+
+#[test]
+fn double_quoted_and_raw_strings() {
+    let db = DbInstance::default();
+    db.run_default(":create airport {code: String => desc: String}")
+        .unwrap();
+    db.run_default(
+        r#"?[code, desc] <- [['ORD', "Chicago O'Hare"], ['LHR', _"London "Heathrow""_]]
+           :put airport {code => desc}"#,
+    )
+    .unwrap();
+    let res = db
+        .run_default(r#"?[code] := *airport{code, desc: "Chicago O'Hare"}"#)
+        .unwrap();
+    assert_eq!(res.into_json()["rows"], json!([["ORD"]]));
+    let res = db
+        .run_default(r#"?[desc] := *airport{code: 'LHR', desc}"#)
+        .unwrap();
+    assert_eq!(res.into_json()["rows"], json!([["London \"Heathrow\""]]));
+    let res = db
+        .run_default(
+            r#"?[s] := s = ___"first line
+no \escapes "here""___"#,
+        )
+        .unwrap();
+    assert_eq!(
+        res.into_json()["rows"],
+        json!([["first line\nno \\escapes \"here\""]])
+    );
+}
+
+#[cfg(any(
+    feature = "storage-sqlite",
+    feature = "storage-rocksdb",
+    feature = "storage-sled"
+))]
+/// A path under the system temp directory for an on-disk test database,
+/// removed on drop. Bind it before the database so the database goes first.
+struct TempDbPath(std::path::PathBuf);
+
+#[cfg(any(
+    feature = "storage-sqlite",
+    feature = "storage-rocksdb",
+    feature = "storage-sled"
+))]
+impl TempDbPath {
+    fn new(name: &str) -> Self {
+        Self(std::env::temp_dir().join(format!("cozo-{}-{}", name, std::process::id())))
+    }
+}
+
+#[cfg(any(
+    feature = "storage-sqlite",
+    feature = "storage-rocksdb",
+    feature = "storage-sled"
+))]
+impl AsRef<std::path::Path> for TempDbPath {
+    fn as_ref(&self) -> &std::path::Path {
+        &self.0
+    }
+}
+
+#[cfg(any(
+    feature = "storage-sqlite",
+    feature = "storage-rocksdb",
+    feature = "storage-sled"
+))]
+impl Drop for TempDbPath {
+    fn drop(&mut self) {
+        if self.0.is_dir() {
+            _ = std::fs::remove_dir_all(&self.0);
+        } else {
+            _ = std::fs::remove_file(&self.0);
+        }
+    }
+}
+
+#[cfg(any(
+    feature = "storage-sqlite",
+    feature = "storage-rocksdb",
+    feature = "storage-sled"
+))]
+/// The engines compiled in that keep their data on disk.
+fn persistent_engines() -> Vec<&'static str> {
+    let mut engines = vec![];
+    #[cfg(feature = "storage-sqlite")]
+    engines.push("sqlite");
+    #[cfg(feature = "storage-rocksdb")]
+    engines.push("rocksdb");
+    #[cfg(feature = "storage-sled")]
+    engines.push("sled");
+    engines
+}
+
+#[cfg(any(
+    feature = "storage-sqlite",
+    feature = "storage-rocksdb",
+    feature = "storage-sled"
+))]
+#[test]
+fn flush_close_and_reopen() {
+    for engine in persistent_engines() {
+        let path = TempDbPath::new(&format!("flush-{engine}"));
+        let db = DbInstance::new(engine, &path, "").unwrap();
+        db.run_default(":create airport {code: String => runways: Int}")
+            .unwrap();
+        db.run_default("?[code, runways] <- [['AUS', 2]] :put airport {code => runways}")
+            .unwrap();
+        db.flush().unwrap();
+        db.close().unwrap();
+
+        let db = DbInstance::new(engine, &path, "").unwrap();
+        let res = db
+            .run_default("?[code, runways] := *airport{code, runways}")
+            .unwrap();
+        assert_eq!(res.into_json()["rows"], json!([["AUS", 2]]));
+        db.close().unwrap();
+    }
+}
+
+#[cfg(feature = "storage-rocksdb")]
+#[test]
+fn rocksdb_temp_databases() {
+    let first = crate::new_cozo_rocksdb_temp().unwrap();
+    let second = crate::new_cozo_rocksdb_temp().unwrap();
+    let first_path = std::path::PathBuf::from(first.db.db_path());
+    assert_ne!(first_path, std::path::PathBuf::from(second.db.db_path()));
+    assert!(first_path.exists());
+
+    first
+        .run_default(":create airport {code: String => city: String}")
+        .unwrap();
+    first
+        .run_default("?[code, city] <- [['AUS', 'Austin']] :put airport {code => city}")
+        .unwrap();
+    let res = first
+        .run_default("?[code, city] := *airport{code, city}")
+        .unwrap();
+    assert_eq!(res.into_json()["rows"], json!([["AUS", "Austin"]]));
+    // the second database does not see the first one's relations
+    assert!(second.run_default("?[code] := *airport{code}").is_err());
+
+    drop(first);
+    assert!(!first_path.exists());
+}
+
+#[cfg(any(
+    feature = "storage-sqlite",
+    feature = "storage-rocksdb",
+    feature = "storage-sled"
+))]
+#[test]
+fn committed_writes_survive_reopen() {
+    for engine in persistent_engines() {
+        let path = TempDbPath::new(&format!("reopen-{engine}"));
+        {
+            let db = DbInstance::new(engine, &path, "").unwrap();
+            db.run_default(":create country {code: String => desc: String}")
+                .unwrap();
+            db.run_default(
+                "?[code, desc] <- [['CU', 'Cuba'], ['CY', 'Cyprus']] :put country {code => desc}",
+            )
+            .unwrap();
+        }
+
+        let db = DbInstance::new(engine, &path, "").unwrap();
+        let res = db
+            .run_default("?[desc] := *country{code: 'CU', desc}")
+            .unwrap();
+        assert_eq!(res.into_json()["rows"], json!([["Cuba"]]));
+    }
+}
+
+#[test]
+fn limit_short_circuits_scan() {
+    let db = DbInstance::default();
+    db.run_default(":create r {k: Any}").unwrap();
+    db.run_default("?[k] := k in int_range(1000) :put r {k}")
+        .unwrap();
+    // strings sort after numbers, so this row is only reached by a full scan
+    db.run_default("?[k] <- [['boom']] :put r {k}").unwrap();
+
+    let res = db
+        .run_default("?[k] := *r{k}, assert(is_num(k)), k > 10 :limit 5")
+        .unwrap();
+    assert_eq!(
+        res.into_json()["rows"],
+        json!([[11], [12], [13], [14], [15]])
+    );
+    assert!(db
+        .run_default("?[k] := *r{k}, assert(is_num(k)), k > 10")
+        .is_err());
+}
+
+#[test]
+fn validity_window() {
+    let db = DbInstance::default();
+    db.run_default(":create runways {code: String, at: Validity => n: Int}")
+        .unwrap();
+    db.run_default(
+        r#"
+        ?[code, at, n] <- [['AUS', [1, true], 1], ['AUS', [5, true], 2],
+                           ['AUS', [10, true], 3], ['AUS', [20, true], 4]]
+        :put runways {code, at => n}
+    "#,
+    )
+    .unwrap();
+    // the version in effect at the start of the window, plus every version
+    // asserted within it
+    let res = db
+        .run_script(
+            r#"
+        ?[from, n] := *runways{code: 'AUS', at, n @ $t1}, from = to_int(at)
+        ?[from, n] := *runways{code: 'AUS', at, n}, from = to_int(at),
+                      between_exclusive(from, $t1, $t2 + 1)
+    "#,
+            BTreeMap::from([("t1".into(), 4.into()), ("t2".into(), 15.into())]),
+            ScriptMutability::Immutable,
+        )
+        .unwrap();
+    assert_eq!(res.into_json()["rows"], json!([[1, 1], [5, 2], [10, 3]]));
+}
+
+#[test]
+fn full_history_of_versions() {
+    let db = DbInstance::default();
+    db.run_default(":create runways {code: String, at: Validity => n: Int}")
+        .unwrap();
+    db.set_clock(|| ValidityTs::from_micros(100));
+    db.run_default("?[code, at, n] <- [['AUS', 'ASSERT', 2]] :put runways {code, at => n}")
+        .unwrap();
+    db.set_clock(|| ValidityTs::from_micros(200));
+    db.run_default("?[code, at, n] <- [['AUS', 'ASSERT', 3]] :put runways {code, at => n}")
+        .unwrap();
+
+    // binding the validity without an `@` clause lists every version
+    let res = db
+        .run_default("?[ts, n] := *runways{code: 'AUS', at, n}, ts = to_int(at)")
+        .unwrap();
+    assert_eq!(res.into_json()["rows"], json!([[100, 2], [200, 3]]));
+    let res = db
+        .run_default("?[n] := *runways{code: 'AUS', n @ 'NOW'}")
+        .unwrap();
+    assert_eq!(res.into_json()["rows"], json!([[3]]));
+}
+
+#[test]
+fn retract_at_current_validity() {
+    let db = DbInstance::default();
+    db.run_default(":create runways {code: String, at: Validity => n: Int}")
+        .unwrap();
+    db.set_clock(|| ValidityTs::from_micros(100));
+    db.run_default("?[code, at, n] <- [['AUS', 'ASSERT', 2]] :put runways {code, at => n}")
+        .unwrap();
+    db.set_clock(|| ValidityTs::from_micros(200));
+    db.run_default("?[code, at, n] <- [['AUS', 'RETRACT', 2]] :put runways {code, at => n}")
+        .unwrap();
+
+    let res = db
+        .run_default("?[n] := *runways{code: 'AUS', n @ 'NOW'}")
+        .unwrap();
+    assert!(res.rows.is_empty());
+    // the retraction is a new version, the assertion before it is kept
+    let res = db
+        .run_default("?[n] := *runways{code: 'AUS', n @ 150}")
+        .unwrap();
+    assert_eq!(res.into_json()["rows"], json!([[2]]));
+    assert_eq!(
+        db.version_count("runways", &[DataValue::from("AUS")])
+            .unwrap(),
+        2
+    );
+}
+
+#[test]
+fn expire_old_assertions() {
+    let db = DbInstance::default();
+    db.run_default(":create hist {code: String, at: Validity => elev: Int}")
+        .unwrap();
+    db.run_default("::index create hist:by_elev {elev, code, at}")
+        .unwrap();
+    db.set_clock(|| ValidityTs::from_micros(100));
+    db.run_default(
+        "?[code, at, elev] <- [['AUS', 'ASSERT', 5], ['LHR', 'ASSERT', 6]] :put hist {code, at => elev}",
+    )
+    .unwrap();
+    db.set_clock(|| ValidityTs::from_micros(300));
+    db.run_default("?[code, at, elev] <- [['LHR', 'ASSERT', 7]] :put hist {code, at => elev}")
+        .unwrap();
+
+    db.set_clock(|| ValidityTs::from_micros(500));
+    assert_eq!(db.expire("hist", ValidityTs::from_micros(200)).unwrap(), 1);
+    assert_eq!(db.expire("hist", ValidityTs::from_micros(200)).unwrap(), 0);
+    let res = db
+        .run_default("?[code, elev] := *hist{code, elev @ 'NOW'}")
+        .unwrap();
+    assert_eq!(res.into_json()["rows"], json!([["LHR", 7]]));
+    let res = db
+        .run_default("?[code, elev] := *hist{code, elev @ 150}")
+        .unwrap();
+    assert_eq!(res.into_json()["rows"], json!([["AUS", 5], ["LHR", 6]]));
+    let res = db
+        .run_default("?[code, ts] := *hist:by_elev{elev: 5, code, at}, ts = to_int(at)")
+        .unwrap();
+    assert_eq!(res.into_json()["rows"], json!([["AUS", 100], ["AUS", 500]]));
+
+    db.run_default(":create airport {code: String => city: String}")
+        .unwrap();
+    assert!(db.expire("airport", ValidityTs::from_micros(200)).is_err());
+}
+
+#[test]
+fn gc_old_versions() {
+    let db = DbInstance::default();
+    db.run_default(":create runways {code: String, at: Validity => n: Int}")
+        .unwrap();
+    db.run_default("::index create runways:by_n {n, code, at}")
+        .unwrap();
+    db.run_default(
+        r#"
+        ?[code, at, n] <- [['AUS', [1, true], 1], ['AUS', [5, true], 2],
+                           ['AUS', [10, true], 3], ['AUS', [20, true], 4],
+                           ['AMS', [1, true], 6]]
+        :put runways {code, at => n}
+    "#,
+    )
+    .unwrap();
+    let as_of = |t: i64| {
+        db.run_default(&format!("?[code, n] := *runways{{code, n @ {t}}}"))
+            .unwrap()
+            .into_json()["rows"]
+            .clone()
+    };
+    assert_eq!(as_of(3), json!([["AMS", 6], ["AUS", 1]]));
+
+    assert_eq!(db.gc(ValidityTs::from_micros(12)).unwrap(), 2);
+
+    assert_eq!(as_of(3), json!([["AMS", 6]]));
+    assert_eq!(as_of(12), json!([["AMS", 6], ["AUS", 3]]));
+    assert_eq!(as_of(25), json!([["AMS", 6], ["AUS", 4]]));
+    let versions = db
+        .run_default("?[count(n)] := *runways{n}")
+        .unwrap()
+        .into_json();
+    assert_eq!(versions["rows"], json!([[3]]));
+    let indexed = db
+        .run_default("?[count(n)] := *runways:by_n{n}")
+        .unwrap()
+        .into_json();
+    assert_eq!(indexed["rows"], json!([[3]]));
+    assert_eq!(db.gc(ValidityTs::from_micros(12)).unwrap(), 0);
+}
+
+#[test]
+fn gc_skips_restricted_relations() {
+    let db = DbInstance::default();
+    for rel in ["locked", "guarded"] {
+        db.run_default(&format!(
+            ":create {rel} {{code: String, at: Validity => n: Int}}"
+        ))
+        .unwrap();
+        db.run_default(&format!(
+            "?[code, at, n] <- [['AUS', [1, true], 1], ['AUS', [5, true], 2]] :put {rel} {{code, at => n}}"
+        ))
+        .unwrap();
+    }
+    db.run_default("::access_level read_only locked").unwrap();
+    db.run_default("::access_level protected guarded").unwrap();
+
+    assert_eq!(db.gc(ValidityTs::from_micros(12)).unwrap(), 0);
+    for rel in ["locked", "guarded"] {
+        let versions = db
+            .run_default(&format!("?[count(n)] := *{rel}{{n}}"))
+            .unwrap()
+            .into_json();
+        assert_eq!(versions["rows"], json!([[2]]));
+    }
+}
+
+#[test]
+fn multi_tx_reads_its_own_writes() {
+    let db = DbInstance::default();
+    db.run_default(":create airport {code: String => city: String}")
+        .unwrap();
+    let tx = db.multi_transaction(true);
+    tx.run_script(
+        "?[code, city] <- [['AUS', 'Austin']] :put airport {code => city}",
+        Default::default(),
+    )
+    .unwrap();
+    let inside = tx
+        .run_script("?[code, city] := *airport{code, city}", Default::default())
+        .unwrap();
+    assert_eq!(inside.into_json()["rows"], json!([["AUS", "Austin"]]));
+    tx.commit().unwrap();
+}
+
+// the mem and sqlite engines block readers while a write transaction is open,
+// so isolation from concurrent readers is checked on sled
+#[cfg(feature = "storage-sled")]
+#[test]
+fn multi_tx_writes_hidden_until_commit() {
+    let path = TempDbPath::new("multi-tx");
+    let db = DbInstance::new("sled", &path, "").unwrap();
+    db.run_default(":create airport {code: String => city: String}")
+        .unwrap();
+    let tx = db.multi_transaction(true);
+    tx.run_script(
+        "?[code, city] <- [['AUS', 'Austin']] :put airport {code => city}",
+        Default::default(),
+    )
+    .unwrap();
+    let inside = tx
+        .run_script("?[code, city] := *airport{code, city}", Default::default())
+        .unwrap();
+    assert_eq!(inside.into_json()["rows"], json!([["AUS", "Austin"]]));
+    let outside = db
+        .run_default("?[code, city] := *airport{code, city}")
+        .unwrap();
+    assert_eq!(outside.into_json()["rows"], json!([]));
+
+    tx.commit().unwrap();
+    let outside = db
+        .run_default("?[code, city] := *airport{code, city}")
+        .unwrap();
+    assert_eq!(outside.into_json()["rows"], json!([["AUS", "Austin"]]));
+}
+
+#[test]
+fn run_scripts_share_a_snapshot() {
+    let db = DbInstance::default();
+    db.run_default(":create counters {k: String => v: Int}")
+        .unwrap();
+    db.run_default("?[k, v] <- [['a', 0], ['b', 0]] :put counters {k => v}")
+        .unwrap();
+
+    let writer = {
+        let db = db.clone();
+        std::thread::spawn(move || {
+            for i in 1..=200 {
+                db.run_script(
+                    "?[k, v] <- [['a', $i], ['b', $i]] :put counters {k => v}",
+                    BTreeMap::from([("i".to_string(), DataValue::from(i))]),
+                    ScriptMutability::Mutable,
+                )
+                .unwrap();
+            }
+        })
+    };
+    for _ in 0..200 {
+        let res = db
+            .run_scripts(
+                &[
+                    "?[v] := *counters{k: 'a', v}",
+                    "?[v] := *counters{k: 'b', v}",
+                ],
+                Default::default(),
+            )
+            .unwrap();
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0].rows, res[1].rows);
+    }
+    writer.join().unwrap();
+
+    assert!(db
+        .run_scripts(
+            &[
+                "?[v] := *counters{v}",
+                "?[k, v] <- [['c', 1]] :put counters {k => v}"
+            ],
+            Default::default(),
+        )
+        .is_err());
+}
+
+#[test]
+fn mixed_int_float_comparison() {
+    let db = DbInstance::default();
+    db.run_default(":create peak {altitude: Int => name: String}")
+        .unwrap();
+    db.run_default(
+        r#"
+        ?[altitude, name] <- [[999, 'a'], [1000, 'b'], [1001, 'c'], [2000, 'd']]
+        :put peak {altitude => name}
+        "#,
+    )
+    .unwrap();
+    let names = |q: &str| db.run_default(q).unwrap().into_json()["rows"].clone();
+    assert_eq!(
+        names("?[name] := *peak{altitude, name}, altitude > 1000.5"),
+        json!([["c"], ["d"]])
+    );
+    assert_eq!(
+        names("?[name] := *peak{altitude, name}, altitude >= 1000.0"),
+        json!([["b"], ["c"], ["d"]])
+    );
+    assert_eq!(
+        names("?[name] := *peak{altitude, name}, altitude >= 1000"),
+        json!([["b"], ["c"], ["d"]])
+    );
+    assert_eq!(
+        names("?[name] := *peak{altitude, name}, altitude <= 1000.0"),
+        json!([["a"], ["b"]])
+    );
+    assert_eq!(
+        names("?[name] := *peak{altitude, name}, altitude < 1000.5, altitude > 999.5"),
+        json!([["b"]])
+    );
+}
+
+#[test]
+fn duplicate_versions_put_or_insert() {
+    let db = DbInstance::default();
+    db.run_default(":create hist {code: String, at: Validity => elev: Int}")
+        .unwrap();
+    let assert_aus = "?[code, at, elev] <- [['AUS', [10, true], 5]]";
+    let versions = || {
+        db.run_default("?[count(at)] := *hist{code: 'AUS', at}")
+            .unwrap()
+            .into_json()["rows"]
+            .clone()
+    };
+
+    // `:put` overwrites the identical version, so re-asserting is a no-op
+    db.run_default(&format!("{assert_aus} :put hist {{code, at => elev}}"))
+        .unwrap();
+    db.run_default(&format!("{assert_aus} :put hist {{code, at => elev}}"))
+        .unwrap();
+    assert_eq!(versions(), json!([[1]]));
+
+    // `:insert` refuses to assert a version that already exists
+    let err = db
+        .run_default(&format!("{assert_aus} :insert hist {{code, at => elev}}"))
+        .unwrap_err();
+    assert_eq!(
+        err.code().unwrap().to_string(),
+        "transact::assertion_failure"
+    );
+    assert_eq!(versions(), json!([[1]]));
+
+    db.run_default("?[code, at, elev] <- [['AUS', [11, true], 5]] :insert hist {code, at => elev}")
+        .unwrap();
+    assert_eq!(versions(), json!([[2]]));
+}
+
+#[test]
+fn version_count_of_row() {
+    let db = DbInstance::default();
+    db.run_default(":create hist {code: String, at: Validity => elev: Int}")
+        .unwrap();
+    db.run_default(":create plain {code: String => elev: Int}")
+        .unwrap();
+    db.run_default(
+        r"
+        ?[code, at, elev] <- [['AUS', [10, true], 5],
+                              ['AUS', [11, false], 5],
+                              ['AUS', [12, true], 6],
+                              ['LHR', [10, true], 7]]
+        :put hist {code, at => elev}
+        ",
+    )
+    .unwrap();
+
+    let count = |code: &str| db.version_count("hist", &[DataValue::from(code)]);
+    assert_eq!(count("AUS").unwrap(), 3);
+    assert_eq!(count("LHR").unwrap(), 1);
+    assert_eq!(count("JFK").unwrap(), 0);
+    assert!(db.version_count("hist", &[]).is_err());
+    assert!(db
+        .version_count("plain", &[DataValue::from("AUS")])
+        .is_err());
+}
+
+#[test]
+fn create_relation_from_def() {
+    let db = DbInstance::default();
+    let def = RelationDef::new("airport")
+        .unwrap()
+        .key(ColumnSpec::new("code").unwrap().string())
+        .column(
+            ColumnSpec::new("city")
+                .unwrap()
+                .string()
+                .description("served city"),
+        )
+        .column(
+            ColumnSpec::new("runways")
+                .unwrap()
+                .int()
+                .default("1")
+                .unwrap(),
+        )
+        .column(ColumnSpec::new("elev").unwrap().float().nullable())
+        .column(ColumnSpec::new("note").unwrap());
+    db.create_relation(&def).unwrap();
+    db.run_default(
+        r#":create airport_dsl {
+            code: String
+            =>
+            city: String "served city",
+            runways: Int default 1,
+            elev: Float?,
+            note
+        }"#,
+    )
+    .unwrap();
+    let columns = |rel: &str| db.run_default(&format!("::columns {rel}")).unwrap().rows;
+    assert_eq!(columns("airport"), columns("airport_dsl"));
+
+    db.run_default("?[code, city] <- [['AUS', 'Austin']] :put airport {code, city}")
+        .unwrap();
+    let res = db
+        .run_default("?[code, city, runways, elev] := *airport{code, city, runways, elev}")
+        .unwrap();
+    assert_eq!(res.into_json()["rows"], json!([["AUS", "Austin", 1, null]]));
+
+    assert!(db.create_relation(&def).is_err());
+
+    assert!(RelationDef::new("air port").is_err());
+    assert!(RelationDef::new("airport {code} :replace x").is_err());
+    assert!(ColumnSpec::new("code: String").is_err());
+    assert!(ColumnSpec::new("").is_err());
+    assert!(ColumnSpec::new("runways").unwrap().default("1 +").is_err());
+}
+
+#[test]
+fn user_relation_ids_above_system() {
+    let db = DbInstance::default();
+    // same name as the key holding the storage version in the system relation
+    db.run_default(":create STORAGE_VERSION {a}").unwrap();
+    db.run_default(":create b {a}").unwrap();
+    db.run_default("?[a] <- [[1]] :put STORAGE_VERSION {a}")
+        .unwrap();
+
+    let db = match db {
+        DbInstance::Mem(db) => db,
+        _ => unreachable!(),
+    };
+    let tx = db.transact().unwrap();
+    let first = tx.get_relation("STORAGE_VERSION", false).unwrap().id;
+    let second = tx.get_relation("b", false).unwrap().id;
+    assert!(first > RelationId::SYSTEM);
+    assert!(second > first);
+    drop(tx);
+
+    // the system relation is untouched and can still be reopened
+    assert!(db.initialize().is_ok());
+    let res = db
+        .run_script(
+            "?[a] := *STORAGE_VERSION{a}",
+            Default::default(),
+            ScriptMutability::Immutable,
+        )
+        .unwrap();
+    assert_eq!(res.into_json()["rows"], json!([[1]]));
+}
+
+#[test]
+fn fixed_clock_validities() {
+    let db = DbInstance::default();
+    db.run_default(":create hist {code: String, at: Validity => elev: Int}")
+        .unwrap();
+    db.set_clock(|| ValidityTs::from_micros(1_000_000));
+    db.run_default("?[code, at, elev] <- [['AUS', 'ASSERT', 5]] :put hist {code, at => elev}")
+        .unwrap();
+    db.run_default("?[code, at, elev] <- [['LHR', 'ASSERT', 6]] :put hist {code, at => elev}")
+        .unwrap();
+    let res = db
+        .run_default("?[code, ts] := *hist{code, at}, ts = to_int(at)")
+        .unwrap();
+    assert_eq!(
+        res.into_json()["rows"],
+        json!([["AUS", 1000000], ["LHR", 1000000]])
+    );
+
+    // reads as of 'NOW' use the injected clock too
+    let res = db.run_default("?[code] := *hist{code @ 'NOW'}").unwrap();
+    assert_eq!(res.rows.len(), 2);
+    db.set_clock(|| ValidityTs::from_micros(999_999));
+    let res = db.run_default("?[code] := *hist{code @ 'NOW'}").unwrap();
+    assert!(res.rows.is_empty());
+
+    db.reset_clock();
+    db.run_default("?[code, at, elev] <- [['AUS', 'ASSERT', 7]] :put hist {code, at => elev}")
+        .unwrap();
+    let res = db
+        .run_default("?[count(at)] := *hist{code: 'AUS', at}")
+        .unwrap();
+    assert_eq!(res.into_json()["rows"], json!([[2]]));
+}
+
+#[test]
+fn run_script_as_of_validity() {
+    let db = DbInstance::default();
+    db.run_default(":create hist {code: String, at: Validity => elev: Int}")
+        .unwrap();
+    db.run_default(
+        "?[code, at, elev] <- [['AUS', [10, true], 5], ['LHR', [10, true], 6]] :put hist {code, at => elev}",
+    )
+    .unwrap();
+    db.run_default(
+        "?[code, at, elev] <- [['AUS', [20, true], 7], ['LHR', [20, false], 6]] :put hist {code, at => elev}",
+    )
+    .unwrap();
+    let query = "?[code, elev] := *hist{code, elev @ 'NOW'}";
+
+    let res = db.run_default(query).unwrap();
+    assert_eq!(res.into_json()["rows"], json!([["AUS", 7]]));
+    let res = db
+        .run_script_at(
+            query,
+            Default::default(),
+            ValidityTs::from_micros(15),
+            ScriptMutability::Immutable,
+        )
+        .unwrap();
+    assert_eq!(res.into_json()["rows"], json!([["AUS", 5], ["LHR", 6]]));
+    let res = db
+        .run_script_at(
+            query,
+            Default::default(),
+            ValidityTs::from_micros(5),
+            ScriptMutability::Immutable,
+        )
+        .unwrap();
+    assert!(res.rows.is_empty());
+
+    // writes with 'ASSERT' are stamped with the given validity
+    db.run_script_at(
+        "?[code, at, elev] <- [['LHR', 'ASSERT', 8]] :put hist {code, at => elev}",
+        Default::default(),
+        ValidityTs::from_micros(30),
+        ScriptMutability::Mutable,
+    )
+    .unwrap();
+    let res = db
+        .run_default("?[elev, ts] := *hist{code: 'LHR', at, elev}, ts = to_int(at)")
+        .unwrap();
+    assert_eq!(res.into_json()["rows"], json!([[6, 10], [6, 20], [8, 30]]));
+}
+
+#[test]
+fn order_with_unicode_collation() {
+    let db = DbInstance::default();
+    let data = "data[city] <- [['Zürich'], ['Ängelholm'], ['zagreb'], ['Aberdeen'], ['Århus'], ['Angers']]";
+    let res = db
+        .run_default(&format!("{data} ?[city] := data[city] :order city"))
+        .unwrap();
+    assert_eq!(
+        res.into_json()["rows"],
+        json!([
+            ["Aberdeen"],
+            ["Angers"],
+            ["Zürich"],
+            ["zagreb"],
+            ["Ängelholm"],
+            ["Århus"]
+        ])
+    );
+    let res = db
+        .run_default(&format!(
+            "{data} ?[city] := data[city] :order city collate unicode"
+        ))
+        .unwrap();
+    assert_eq!(
+        res.into_json()["rows"],
+        json!([
+            ["Aberdeen"],
+            ["Ängelholm"],
+            ["Angers"],
+            ["Århus"],
+            ["zagreb"],
+            ["Zürich"]
+        ])
+    );
+    let res = db
+        .run_default(&format!(
+            "{data} ?[city] := data[city] :order -city collate unicode :limit 2"
+        ))
+        .unwrap();
+    assert_eq!(res.into_json()["rows"], json!([["Zürich"], ["zagreb"]]));
+    assert!(db
+        .run_default(&format!(
+            "{data} ?[city] := data[city] :order city collate klingon"
+        ))
+        .is_err());
+}
+
+#[test]
+fn warmup_relations() {
+    let db = DbInstance::default();
+    db.run_default(":create airport {code: String => city: String}")
+        .unwrap();
+    db.run_default(":create route {fr: String, to: String}")
+        .unwrap();
+    db.run_default(
+        "?[code, city] <- [['AUS', 'Austin'], ['LHR', 'London']] :put airport {code => city}",
+    )
+    .unwrap();
+    db.run_default("?[fr, to] <- [['AUS', 'LHR']] :put route {fr, to}")
+        .unwrap();
+
+    assert_eq!(db.warmup(["airport", "route"].iter()).unwrap(), 3);
+    let res = db
+        .run_default("?[city] := *route{fr: 'AUS', to}, *airport{code: to, city}")
+        .unwrap();
+    assert_eq!(res.into_json()["rows"], json!([["London"]]));
+    assert!(db.warmup(["no_such_relation"].iter()).is_err());
+}
+
+#[test]
+fn many_values_per_entity() {
+    let db = DbInstance::default();
+    db.run_default(":create airport {code: String => city: String}")
+        .unwrap();
+    // making the value part of the key lets one airport hold several tags
+    db.run_default(":create airport_tag {code: String, tag: String}")
+        .unwrap();
+    db.run_default(
+        "?[code, city] <- [['AUS', 'Austin'], ['LHR', 'London']] :put airport {code => city}",
+    )
+    .unwrap();
+    db.run_default("?[code, tag] <- [['AUS', 'hub']] :put airport_tag {code, tag}")
+        .unwrap();
+    db.run_default(
+        "?[code, tag] <- [['AUS', 'international'], ['AUS', 'texas'], ['LHR', 'hub']] :put airport_tag {code, tag}",
+    )
+    .unwrap();
+
+    let res = db
+        .run_default("?[city, tag] := *airport{code: 'AUS', city}, *airport_tag{code: 'AUS', tag}")
+        .unwrap();
+    assert_eq!(
+        res.into_json()["rows"],
+        json!([
+            ["Austin", "hub"],
+            ["Austin", "international"],
+            ["Austin", "texas"]
+        ])
+    );
+}
+
+#[test]
+fn replicate_between_dbs() {
+    let source = DbInstance::default();
+    let target = DbInstance::default();
+    for db in [&source, &target] {
+        db.run_default(":create airport {code: String => city: String}")
+            .unwrap();
+        db.run_default(":create hist {code: String, at: Validity => elev: Int}")
+            .unwrap();
+    }
+    source
+        .run_default(
+            "?[code, city] <- [['AUS', 'Austin'], ['LHR', 'London']] :put airport {code => city}",
+        )
+        .unwrap();
+    source
+        .run_default(
+            "?[code, at, elev] <- [['AUS', [10, true], 5], ['LHR', [10, true], 7]] :put hist {code, at => elev}",
+        )
+        .unwrap();
+    target
+        .run_default(
+            "?[code, city] <- [['LHR', 'London'], ['JFK', 'New York']] :put airport {code => city}",
+        )
+        .unwrap();
+
+    let relations = ["airport", "hist"];
+    let written = source
+        .replicate_to(&target, relations.iter(), None)
+        .unwrap();
+    // three rows written, and JFK removed as it is missing from the source
+    assert_eq!(written, 4);
+    let airports = "?[code, city] := *airport{code, city}";
+    let history = "?[code, at, elev] := *hist{code, at, elev}";
+    assert_eq!(
+        target.run_default(airports).unwrap().rows,
+        source.run_default(airports).unwrap().rows
+    );
+    assert_eq!(
+        target.run_default(history).unwrap().rows,
+        source.run_default(history).unwrap().rows
+    );
+
+    source
+        .run_default("?[code, city] <- [['AUS', 'Austin TX']] :put airport {code => city}")
+        .unwrap();
+    source
+        .run_default(
+            "?[code, at, elev] <- [['AUS', [20, true], 6], ['LHR', [12, true], 8]] :put hist {code, at => elev}",
+        )
+        .unwrap();
+    let written = source
+        .replicate_to(&target, relations.iter(), Some(ValidityTs::from_micros(15)))
+        .unwrap();
+    assert_eq!(written, 2);
+    assert_eq!(
+        target
+            .run_default("?[city] := *airport{code: 'AUS', city}")
+            .unwrap()
+            .into_json()["rows"],
+        json!([["Austin TX"]])
+    );
+    // the LHR version predates `since`, so it is not shipped
+    assert_eq!(
+        target.run_default(history).unwrap().into_json()["rows"],
+        json!([
+            ["AUS", [20, true], 6],
+            ["AUS", [10, true], 5],
+            ["LHR", [10, true], 7]
+        ])
+    );
+    assert_eq!(
+        source
+            .replicate_to(&target, relations.iter(), None)
+            .unwrap(),
+        1
+    );
+
+    source
+        .run_default("?[code] <- [['LHR']] :rm airport {code}")
+        .unwrap();
+    source
+        .run_default("?[code, at] <- [['AUS', [10, true]]] :rm hist {code, at}")
+        .unwrap();
+    assert_eq!(
+        source
+            .replicate_to(&target, relations.iter(), None)
+            .unwrap(),
+        2
+    );
+    assert_eq!(
+        target.run_default(airports).unwrap().into_json()["rows"],
+        json!([["AUS", "Austin TX"]])
+    );
+    assert_eq!(
+        target.run_default(history).unwrap().rows,
+        source.run_default(history).unwrap().rows
+    );
+}
+
+#[test]
+fn bool_column_filter() {
+    let db = DbInstance::default();
+    db.run_default(":create airport_flags {code: String => international: Bool}")
+        .unwrap();
+    db.run_default(
+        r"
+        ?[code, international] <- [['AUS', true], ['LHR', true], ['BGC', false]]
+        :put airport_flags {code => international}
+        ",
+    )
+    .unwrap();
+
+    let res = db
+        .run_default("?[code] := *airport_flags{code, international: true}")
+        .unwrap();
+    assert_eq!(res.into_json()["rows"], json!([["AUS"], ["LHR"]]));
+    let res = db
+        .run_default("?[code] := *airport_flags{code, international}, !international")
+        .unwrap();
+    assert_eq!(res.into_json()["rows"], json!([["BGC"]]));
+    // false sorts before true
+    let res = db
+        .run_default(
+            r"
+            ?[international, code] := *airport_flags{code, international}
+            :order international, code
+            ",
+        )
+        .unwrap();
+    assert_eq!(
+        res.into_json()["rows"],
+        json!([[false, "BGC"], [true, "AUS"], [true, "LHR"]])
+    );
+    assert!(db
+        .run_default(
+            "?[code, international] <- [['CAT', 'yes']] :put airport_flags {code => international}"
+        )
+        .is_err());
+}
+
+#[test]
+fn yield_hook_calls() {
+    let db = DbInstance::default();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    db.set_yield_hook(100, move || {
+        counter.fetch_add(1, Ordering::Relaxed);
+    });
+
+    let res = db.run_default("?[x] := x in int_range(1000)").unwrap();
+    assert_eq!(res.rows.len(), 1000);
+    assert_eq!(calls.load(Ordering::Relaxed), 10);
+
+    // each rule body counts its own tuples
+    calls.store(0, Ordering::Relaxed);
+    db.run_default(
+        r"
+        a[x] := x in int_range(250)
+        ?[x] := a[x]
+        ",
+    )
+    .unwrap();
+    assert_eq!(calls.load(Ordering::Relaxed), 4);
+
+    // stored tuples are counted as they are scanned, even if a filter drops them
+    db.run_default(":create r {k: Int => v: Int}").unwrap();
+    db.run_default("?[k, v] := k in int_range(1000), v = k * 2 :put r {k => v}")
+        .unwrap();
+    calls.store(0, Ordering::Relaxed);
+    let res = db.run_default("?[k] := *r{k, v}, v == -1").unwrap();
+    assert!(res.rows.is_empty());
+    assert!(calls.load(Ordering::Relaxed) >= 10);
+
+    db.clear_yield_hook();
+    calls.store(0, Ordering::Relaxed);
+    db.run_default("?[x] := x in int_range(1000)").unwrap();
+    assert_eq!(calls.load(Ordering::Relaxed), 0);
+}
+
+#[test]
+fn filter_by_timestamp_threshold() {
+    let db = DbInstance::default();
+    db.run_default(":create departure {flight: String => at: Float}")
+        .unwrap();
+    db.run_default(
+        r"
+        raw[flight, at] <- [['BA1', '2023-01-15T09:00:00Z'],
+                            ['BA2', '2023-06-01T00:00:00Z'],
+                            ['BA3', '2023-07-01T10:30:00+02:00']]
+        ?[flight, at] := raw[flight, s], at = parse_timestamp(s)
+        :put departure {flight => at}
+        ",
+    )
+    .unwrap();
+
+    let res = db
+        .run_default(
+            r"
+            ?[flight, ts] := *departure{flight, at},
+                             at >= parse_timestamp('2023-06-01T00:00:00Z'),
+                             ts = format_timestamp(at)
+            ",
+        )
+        .unwrap();
+    assert_eq!(
+        res.into_json()["rows"],
+        json!([
+            ["BA2", "2023-06-01T00:00:00+00:00"],
+            ["BA3", "2023-07-01T08:30:00+00:00"]
+        ])
+    );
+    assert!(db
+        .run_default("?[x] := x = parse_timestamp('June 1st')")
+        .is_err());
+}
+
+#[test]
+fn list_valued_columns() {
+    let db = DbInstance::default();
+    db.run_default(":create route_path {stops: [String] => legs: [[Float; 2]]}")
+        .unwrap();
+    db.run_default(
+        r"
+        ?[stops, legs] <- [[['AUS', 'DFW', 'LHR'], [[30.19, -97.67], [32.9, -97.04], [51.47, -0.46]]],
+                           [['AUS', 'LHR'], [[30.19, -97.67], [51.47, -0.46]]]]
+        :put route_path {stops => legs}
+        ",
+    )
+    .unwrap();
+
+    // nested lists round-trip through the key and value encodings
+    let res = db
+        .run_default("?[stops, legs] := *route_path{stops, legs}, stops == ['AUS', 'LHR']")
+        .unwrap();
+    assert_eq!(
+        res.into_json()["rows"],
+        json!([[["AUS", "LHR"], [[30.19, -97.67], [51.47, -0.46]]]])
+    );
+
+    let res = db
+        .run_default(
+            r"
+            ?[n, via, first_lat, all_stops] := *route_path{stops, legs},
+                                               n = length(stops), n > 2,
+                                               via = get(stops, 1),
+                                               first_lat = get(get(legs, 0), 0),
+                                               all_stops = stops ++ ['JFK']
+            ",
+        )
+        .unwrap();
+    assert_eq!(
+        res.into_json()["rows"],
+        json!([[3, "DFW", 30.19, ["AUS", "DFW", "LHR", "JFK"]]])
+    );
+    assert!(db
+        .run_default("?[stops, legs] <- [[['AUS'], [[1.0]]]] :put route_path {stops => legs}")
+        .is_err());
+}
+
+#[test]
+fn join_atoms_at_different_validities() {
+    let db = DbInstance::default();
+    db.run_default(":create country {code: String, at: Validity => name: String}")
+        .unwrap();
+    db.run_default(":create route {fr: String, to: String, at: Validity => dist: Int}")
+        .unwrap();
+    db.run_default(
+        r#"
+        ?[code, at, name] <- [['YU', [10, true], 'Yugoslavia'], ['YU', [20, false], ''],
+                              ['RS', [20, true], 'Serbia']]
+        :put country {code, at => name}
+    "#,
+    )
+    .unwrap();
+    db.run_default(
+        r#"
+        ?[fr, to, at, dist] <- [['BEG', 'LHR', [5, true], 2000], ['BEG', 'LHR', [25, true], 1900]]
+        :put route {fr, to, at => dist}
+    "#,
+    )
+    .unwrap();
+
+    let joined = |t1: i64, t2: i64| {
+        db.run_default(&format!(
+            r#"
+            ?[name, dist] := *country{{name @ {t1}}}, *route{{fr: 'BEG', dist @ {t2}}}
+            "#
+        ))
+        .unwrap()
+        .into_json()["rows"]
+            .clone()
+    };
+    assert_eq!(joined(15, 30), json!([["Yugoslavia", 1900]]));
+    assert_eq!(joined(30, 15), json!([["Serbia", 2000]]));
+    assert_eq!(joined(30, 30), json!([["Serbia", 1900]]));
+    assert_eq!(joined(15, 1), json!([]));
+}
+
+#[test]
+fn lenient_missing_relations() {
+    let db = DbInstance::default();
+    db.run_default(":create airport {code: String => city: String}")
+        .unwrap();
+    db.run_default(
+        "?[code, city] <- [['AUS', 'Austin'], ['LHR', 'London']] :put airport {code => city}",
+    )
+    .unwrap();
+
+    let query = "?[code, terminal] := *airport{code}, *terminal{code, name: terminal}";
+    assert!(db.run_default(query).is_err());
+    let res = db.run_default(&format!("{query} :lenient")).unwrap();
+    assert_eq!(res.headers, ["code", "terminal"]);
+    assert!(res.rows.is_empty());
+
+    let res = db
+        .run_default(
+            r"
+            ?[code, terminal] := *airport{code}, *terminal[code, terminal]
+            ?[code, terminal] := *airport{code, city: terminal}, not *closed{code}
+            :lenient
+            ",
+        )
+        .unwrap();
+    assert_eq!(
+        res.into_json()["rows"],
+        json!([["AUS", "Austin"], ["LHR", "London"]])
+    );
+
+    let res = db
+        .run_default(
+            r"
+            ?[code, terminal] := *airport{code}, optional *terminal{code, name: terminal}
+            :lenient
+            ",
+        )
+        .unwrap();
+    assert_eq!(
+        res.into_json()["rows"],
+        json!([["AUS", null], ["LHR", null]])
+    );
+}
@@ -12,9 +12,11 @@ use std::sync::Arc;
 use miette::{bail, Result};
 use crate::data::program::ReturnMutation;
 
+use crate::data::aggr::AggrLimits;
 use crate::data::tuple::TupleT;
 use crate::data::value::DataValue;
 use crate::fts::TokenizerCache;
+use crate::query::ra::{QueryProfile, YieldHook};
 use crate::{CallbackOp, NamedRows};
 use crate::runtime::callback::CallbackCollector;
 use crate::runtime::relation::RelationId;
@@ -27,6 +29,9 @@ pub struct SessionTx<'a> {
     pub(crate) relation_store_id: Arc<AtomicU64>,
     pub(crate) temp_store_id: AtomicU32,
     pub(crate) tokenizers: Arc<TokenizerCache>,
+    pub(crate) profile: Option<QueryProfile>,
+    pub(crate) aggr_limits: AggrLimits,
+    pub(crate) yield_hook: Option<YieldHook>,
 }
 
 pub const CURRENT_STORAGE_VERSION: [u8; 1] = [0x00];
@@ -40,6 +40,12 @@ pub trait Storage<'s>: Send + Sync + Clone {
     /// have the concept of compaction.
     fn range_compact(&'s self, lower: &[u8], upper: &[u8]) -> Result<()>;
 
+    /// Flush buffered writes to durable storage. The default implementation is
+    /// a no-op, which is correct for engines that persist on commit.
+    fn flush(&'s self) -> Result<()> {
+        Ok(())
+    }
+
     /// Put multiple key-value pairs into the database.
     /// No duplicate data will be sent, and the order data come in is strictly ascending.
     /// There will be no other access to the database while this function is running.
@@ -110,6 +110,21 @@ pub fn new_cozo_rocksdb(path: impl AsRef<Path>) -> Result<Db<RocksDbStorage>> {
     Ok(ret)
 }
 
+/// Creates a RocksDB database in a new, uniquely named directory under the
+/// system temporary directory. The directory is removed once the database
+/// and every clone of it have been dropped.
+pub fn new_cozo_rocksdb_temp() -> Result<Db<RocksDbStorage>> {
+    let db = DbBuilder::default()
+        .temp()
+        .use_capped_prefix_extractor(true, KEY_PREFIX_LEN)
+        .use_bloom_filter(true, 9.9, true)
+        .build()?;
+
+    let ret = Db::new(RocksDbStorage::new(db))?;
+    ret.initialize()?;
+    Ok(ret)
+}
+
 /// RocksDB storage engine
 #[derive(Clone)]
 pub struct RocksDbStorage {
@@ -120,6 +135,10 @@ impl RocksDbStorage {
     pub(crate) fn new(db: RocksDb) -> Self {
         Self { db }
     }
+    #[cfg(test)]
+    pub(crate) fn db_path(&self) -> String {
+        self.db.db_path()
+    }
 }
 
 impl Storage<'_> for RocksDbStorage {
@@ -138,6 +157,10 @@ impl Storage<'_> for RocksDbStorage {
         self.db.range_compact(lower, upper).into_diagnostic()
     }
 
+    fn flush(&self) -> Result<()> {
+        self.db.flush().into_diagnostic()
+    }
+
     fn batch_put<'a>(
         &'a self,
         data: Box<dyn Iterator<Item = Result<(Vec<u8>, Vec<u8>)>> + 'a>,
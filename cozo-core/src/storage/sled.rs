@@ -59,6 +59,11 @@ impl Storage<'_> for SledStorage {
         Ok(())
     }
 
+    fn flush(&self) -> Result<()> {
+        self.db.flush().into_diagnostic()?;
+        Ok(())
+    }
+
     fn batch_put<'a>(
         &'a self,
         data: Box<dyn Iterator<Item = Result<(Vec<u8>, Vec<u8>)>> + 'a>,
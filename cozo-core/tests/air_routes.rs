@@ -7,6 +7,7 @@
  */
 #![cfg(feature = "graph-algo")]
 
+use std::collections::BTreeMap;
 use std::env;
 use std::str::FromStr;
 use std::time::Instant;
@@ -16,7 +17,7 @@ use env_logger::Env;
 use lazy_static::{initialize, lazy_static};
 use serde_json::json;
 
-use cozo::DbInstance;
+use cozo::{DataValue, DbInstance, ScriptMutability};
 
 lazy_static! {
     static ref TEST_DB: DbInstance = {
@@ -30,127 +31,171 @@ lazy_static! {
         let db = DbInstance::new(&db_kind, path, Default::default()).unwrap();
         dbg!(creation.elapsed());
 
-        let init = Instant::now();
-        db.run_default(r##"
-            res[idx, label, typ, code, icao, desc, region, runways, longest, elev, country, city, lat, lon] <~
-                CsvReader(types: ['Int', 'Any', 'Any', 'Any', 'Any', 'Any', 'Any', 'Int?', 'Float?', 'Float?', 'Any', 'Any', 'Float?', 'Float?'],
-                          url: 'file://./tests/air-routes-latest-nodes.csv',
-                          has_headers: true)
-
-            ?[code, icao, desc, region, runways, longest, elev, country, city, lat, lon] :=
-                res[idx, label, typ, code, icao, desc, region, runways, longest, elev, country, city, lat, lon],
-                label == 'airport'
-
-            :replace airport {
-                code: String
-                =>
-                icao: String,
-                desc: String,
-                region: String,
-                runways: Int,
-                longest: Float,
-                elev: Float,
-                country: String,
-                city: String,
-                lat: Float,
-                lon: Float
-            }
-        "##).unwrap();
-
-        db.run_default(
-            r##"
-            res[idx, label, typ, code, icao, desc] <~
-                CsvReader(types: ['Int', 'Any', 'Any', 'Any', 'Any', 'Any'],
-                          url: 'file://./tests/air-routes-latest-nodes.csv',
-                          has_headers: true)
-            ?[code, desc] :=
-                res[idx, label, typ, code, icao, desc],
-                label == 'country'
-
-            :replace country {
-                code: String
-                =>
-                desc: String
-            }
-        "##,
-        )
-        .unwrap();
-
-        db.run_default(
-            r##"
-            res[idx, label, typ, code, icao, desc] <~
-                CsvReader(types: ['Int', 'Any', 'Any', 'Any', 'Any', 'Any'],
-                          url: 'file://./tests/air-routes-latest-nodes.csv',
-                          has_headers: true)
-            ?[idx, code, desc] :=
-                res[idx, label, typ, code, icao, desc],
-                label == 'continent'
-
-            :replace continent {
-                code: String
-                =>
-                desc: String
-            }
-        "##,
-        )
-        .unwrap();
-
-        db.run_default(
-            r##"
-            res[idx, label, typ, code] <~
-                CsvReader(types: ['Int', 'Any', 'Any', 'Any'],
-                          url: 'file://./tests/air-routes-latest-nodes.csv',
-                          has_headers: true)
-            ?[idx, code] :=
-                res[idx, label, typ, code],
-
-            :replace idx2code { idx: Int => code: String }
-        "##,
-        )
-        .unwrap();
-
-        db.run_default(
-            r##"
-            res[] <~
-                CsvReader(types: ['Int', 'Int', 'Int', 'String', 'Float?'],
-                          url: 'file://./tests/air-routes-latest-edges.csv',
-                          has_headers: true)
-            ?[fr, to, dist] :=
-                res[idx, fr_i, to_i, typ, dist],
-                typ == 'route',
-                *idx2code[fr_i, fr],
-                *idx2code[to_i, to]
-
-            :replace route { fr: String, to: String => dist: Float }
-        "##,
-        )
-        .unwrap();
-
-        db.run_default(
-            r##"
-            res[] <~
-                CsvReader(types: ['Int', 'Int', 'Int', 'String'],
-                          url: 'file://./tests/air-routes-latest-edges.csv',
-                          has_headers: true)
-            ?[entity, contained] :=
-                res[idx, fr_i, to_i, typ],
-                typ == 'contains',
-                *idx2code[fr_i, entity],
-                *idx2code[to_i, contained]
-
-
-            :replace contain { entity: String, contained: String }
-        "##,
-        )
-        .unwrap();
-
-        db.run_default("::remove idx2code").unwrap();
-
-        dbg!(init.elapsed());
+        load_air_routes(&db);
         db
     };
 }
 
+fn load_air_routes(db: &DbInstance) {
+    let init = Instant::now();
+    db.run_default(r##"
+        res[idx, label, typ, code, icao, desc, region, runways, longest, elev, country, city, lat, lon] <~
+            CsvReader(types: ['Int', 'Any', 'Any', 'Any', 'Any', 'Any', 'Any', 'Int?', 'Float?', 'Float?', 'Any', 'Any', 'Float?', 'Float?'],
+                      url: 'file://./tests/air-routes-latest-nodes.csv',
+                      has_headers: true)
+
+        ?[code, icao, desc, region, runways, longest, elev, country, city, lat, lon] :=
+            res[idx, label, typ, code, icao, desc, region, runways, longest, elev, country, city, lat, lon],
+            label == 'airport'
+
+        :replace airport {
+            code: String
+            =>
+            icao: String,
+            desc: String,
+            region: String,
+            runways: Int,
+            longest: Float,
+            elev: Float,
+            country: String,
+            city: String,
+            lat: Float,
+            lon: Float
+        }
+    "##).unwrap();
+
+    db.run_default(
+        r##"
+        res[idx, label, typ, code, icao, desc] <~
+            CsvReader(types: ['Int', 'Any', 'Any', 'Any', 'Any', 'Any'],
+                      url: 'file://./tests/air-routes-latest-nodes.csv',
+                      has_headers: true)
+        ?[code, desc] :=
+            res[idx, label, typ, code, icao, desc],
+            label == 'country'
+
+        :replace country {
+            code: String
+            =>
+            desc: String
+        }
+    "##,
+    )
+    .unwrap();
+
+    db.run_default(
+        r##"
+        res[idx, label, typ, code, icao, desc] <~
+            CsvReader(types: ['Int', 'Any', 'Any', 'Any', 'Any', 'Any'],
+                      url: 'file://./tests/air-routes-latest-nodes.csv',
+                      has_headers: true)
+        ?[idx, code, desc] :=
+            res[idx, label, typ, code, icao, desc],
+            label == 'continent'
+
+        :replace continent {
+            code: String
+            =>
+            desc: String
+        }
+    "##,
+    )
+    .unwrap();
+
+    db.run_default(
+        r##"
+        res[idx, label, typ, code] <~
+            CsvReader(types: ['Int', 'Any', 'Any', 'Any'],
+                      url: 'file://./tests/air-routes-latest-nodes.csv',
+                      has_headers: true)
+        ?[idx, code] :=
+            res[idx, label, typ, code],
+
+        :replace idx2code { idx: Int => code: String }
+    "##,
+    )
+    .unwrap();
+
+    db.run_default(
+        r##"
+        res[] <~
+            CsvReader(types: ['Int', 'Int', 'Int', 'String', 'Float?'],
+                      url: 'file://./tests/air-routes-latest-edges.csv',
+                      has_headers: true)
+        ?[fr, to, dist] :=
+            res[idx, fr_i, to_i, typ, dist],
+            typ == 'route',
+            *idx2code[fr_i, fr],
+            *idx2code[to_i, to]
+
+        :replace route { fr: String, to: String => dist: Float }
+    "##,
+    )
+    .unwrap();
+
+    db.run_default(
+        r##"
+        res[] <~
+            CsvReader(types: ['Int', 'Int', 'Int', 'String'],
+                      url: 'file://./tests/air-routes-latest-edges.csv',
+                      has_headers: true)
+        ?[entity, contained] :=
+            res[idx, fr_i, to_i, typ],
+            typ == 'contains',
+            *idx2code[fr_i, entity],
+            *idx2code[to_i, contained]
+
+
+        :replace contain { entity: String, contained: String }
+    "##,
+    )
+    .unwrap();
+
+    db.run_default("::remove idx2code").unwrap();
+
+    dbg!(init.elapsed());
+}
+
+#[cfg(feature = "storage-rocksdb")]
+#[test]
+fn air_routes_on_temp_rocksdb() {
+    let air_routes_on_temp_rocksdb = Instant::now();
+
+    // each run gets a fresh directory, so repeated runs must see the same state
+    for _ in 0..2 {
+        let db = DbInstance::RocksDb(cozo::new_cozo_rocksdb_temp().unwrap());
+        load_air_routes(&db);
+        let relations = db.run_default("::relations").unwrap().into_json();
+        let names = relations["rows"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|row| row[0].as_str().unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec!["airport", "contain", "continent", "country", "route"]
+        );
+        let rows = db
+            .run_default("?[count(fr)] := *route{fr}")
+            .unwrap()
+            .into_json();
+        assert_eq!(rows["rows"], json!([[50637]]));
+        let rows = db
+            .run_default(
+                r#"
+            starting[] <- [['PEK']]
+            ?[] <~ DFS(*route[], *airport[code], starting[], condition: (code == 'LHR'))
+        "#,
+            )
+            .unwrap()
+            .rows;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0][1].get_str().unwrap(), "LHR");
+    }
+    dbg!(air_routes_on_temp_rocksdb.elapsed());
+}
+
 #[test]
 fn dfs() {
     initialize(&TEST_DB);
@@ -365,6 +410,71 @@ fn starts_with() {
     dbg!(starts_with.elapsed());
 }
 
+#[test]
+fn canadian_airports_by_string_predicates() {
+    initialize(&TEST_DB);
+    let canadian_airports_by_string_predicates = Instant::now();
+
+    let rows = TEST_DB
+        .run_default("?[count(code)] := *airport{code, country: 'CA'}, starts_with(code, 'Y')")
+        .unwrap()
+        .rows;
+    assert_eq!(rows[0][0], DataValue::from(182));
+
+    let rows = TEST_DB
+        .run_default(
+            r#"
+        ?[code, city] := *airport{code, city, country: 'CA'}, ends_with(city, 'ville')
+    "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(
+        rows["rows"],
+        json!([
+            ["YBG", "Bagotville"],
+            ["YJT", "Stephenville"],
+            ["YKL", "Schefferville"],
+            ["YSO", "Postville"]
+        ])
+    );
+
+    let rows = TEST_DB
+        .run_default(
+            r#"
+        ?[code] := *airport{code, desc, country: 'CA'},
+                   starts_with(code, 'Y'),
+                   str_includes(lowercase(desc), 'international')
+    "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(
+        rows["rows"],
+        json!([
+            ["YEG"],
+            ["YHM"],
+            ["YHZ"],
+            ["YLW"],
+            ["YOW"],
+            ["YQB"],
+            ["YQM"],
+            ["YQR"],
+            ["YQX"],
+            ["YUL"],
+            ["YVR"],
+            ["YWG"],
+            ["YXE"],
+            ["YXY"],
+            ["YYC"],
+            ["YYJ"],
+            ["YYT"],
+            ["YYZ"]
+        ])
+    );
+    dbg!(canadian_airports_by_string_predicates.elapsed());
+}
+
 #[test]
 fn range_check() {
     initialize(&TEST_DB);
@@ -387,6 +497,95 @@ fn range_check() {
     dbg!(range_check.elapsed());
 }
 
+#[test]
+fn between_check() {
+    initialize(&TEST_DB);
+    let between_check = Instant::now();
+
+    let by_between = TEST_DB
+        .run_default("?[code, runways] := *airport{code, runways}, between(runways, 6, 10)")
+        .unwrap()
+        .into_json();
+    let by_comparisons = TEST_DB
+        .run_default("?[code, runways] := *airport{code, runways}, runways >= 6, runways <= 10")
+        .unwrap()
+        .into_json();
+    assert_eq!(by_between["rows"], by_comparisons["rows"]);
+    assert!(!by_between["rows"].as_array().unwrap().is_empty());
+
+    let by_between = TEST_DB
+        .run_default(
+            "?[code, runways] := *airport{code, runways}, between_exclusive(runways, 6, 10)",
+        )
+        .unwrap()
+        .into_json();
+    let by_comparisons = TEST_DB
+        .run_default("?[code, runways] := *airport{code, runways}, runways > 6, runways < 10")
+        .unwrap()
+        .into_json();
+    assert_eq!(by_between["rows"], by_comparisons["rows"]);
+
+    let rows = TEST_DB
+        .run_default("?[to] := *route{fr: 'PEK', to}, between(to, 'LAX', 'LHR')")
+        .unwrap()
+        .into_json();
+    assert_eq!(rows["rows"], json!([["LAX"], ["LED"], ["LGW"], ["LHR"]]));
+    dbg!(between_check.elapsed());
+}
+
+#[test]
+fn lookup_many_by_key() {
+    initialize(&TEST_DB);
+    let lookup_many_by_key = Instant::now();
+
+    let query = "?[code, city] := code in ['AUS', 'AMS', 'JFK'], *airport{code, city}";
+    let plan = TEST_DB
+        .run_default(&format!("::explain {{ {query} }}"))
+        .unwrap()
+        .into_json();
+    let ops = plan["rows"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|row| row[4].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert!(ops.contains(&"stored_prefix_join"));
+
+    let rows = TEST_DB.run_default(query).unwrap().into_json();
+    assert_eq!(
+        rows["rows"],
+        json!([["AMS", "Amsterdam"], ["AUS", "Austin"], ["JFK", "New York"]])
+    );
+    dbg!(lookup_many_by_key.elapsed());
+}
+
+#[test]
+fn not_in_list() {
+    initialize(&TEST_DB);
+    let not_in_list = Instant::now();
+
+    let all = TEST_DB
+        .run_default("?[code] := *airport{code, region: 'US-TX'}")
+        .unwrap()
+        .into_json();
+    let rows = TEST_DB
+        .run_default(
+            "?[code] := *airport{code, region: 'US-TX'}, code not in ['AUS', 'AMS', 'XXX']",
+        )
+        .unwrap()
+        .into_json();
+    let expected = all["rows"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|row| row[0] != "AUS")
+        .cloned()
+        .collect::<Vec<_>>();
+    assert_eq!(rows["rows"].as_array().unwrap(), &expected);
+    assert_eq!(expected.len() + 1, all["rows"].as_array().unwrap().len());
+    dbg!(not_in_list.elapsed());
+}
+
 #[test]
 fn no_airports() {
     initialize(&TEST_DB);
@@ -414,6 +613,35 @@ fn no_airports() {
     dbg!(no_airports.elapsed());
 }
 
+#[test]
+fn negation_safety() {
+    initialize(&TEST_DB);
+    let negation_safety = Instant::now();
+
+    let expected = json!([
+        ["Andorra"],
+        ["Liechtenstein"],
+        ["Monaco"],
+        ["Pitcairn"],
+        ["San Marino"]
+    ]);
+    for query in [
+        "?[desc] := *country{code, desc}, not *airport{country: code, city}",
+        "?[desc] := c = code, *country{code, desc}, not *airport{country: c}",
+        "?[desc] := not *airport{country: c}, c = code, *country{code, desc}",
+    ] {
+        let rows = TEST_DB.run_default(query).unwrap().into_json();
+        assert_eq!(rows["rows"], expected, "{query}");
+    }
+
+    let err = TEST_DB
+        .run_default("?[desc] := *country{desc}, not *airport{country: c}")
+        .unwrap_err();
+    assert_eq!(err.code().unwrap().to_string(), "eval::unsafe_negation");
+    assert!(err.to_string().ends_with(": c"), "{err}");
+    dbg!(negation_safety.elapsed());
+}
+
 #[test]
 fn no_routes_airport() {
     initialize(&TEST_DB);
@@ -471,6 +699,307 @@ fn runway_distribution() {
     dbg!(no_routes_airports.elapsed());
 }
 
+#[test]
+fn elevation_histogram() {
+    initialize(&TEST_DB);
+    let elevation_histogram = Instant::now();
+
+    let rows = TEST_DB
+        .run_default(
+            r#"
+        ?[bucket, count(code)] := *airport{code, elev}, bucket = bucket(elev, 0, 5000, 500)
+    "#,
+        )
+        .unwrap()
+        .into_json();
+
+    assert_eq!(
+        rows["rows"],
+        json!([
+            [null, 173],
+            [0, 2078],
+            [1, 445],
+            [2, 254],
+            [3, 144],
+            [4, 120],
+            [5, 76],
+            [6, 74],
+            [7, 58],
+            [8, 45],
+            [9, 37]
+        ])
+    );
+    dbg!(elevation_histogram.elapsed());
+}
+
+#[test]
+fn same_city_pairs() {
+    initialize(&TEST_DB);
+    let same_city_pairs = Instant::now();
+
+    let rows = TEST_DB
+        .run_default(
+            r#"
+        ?[a, b] := *airport{code: a, city: c1, country: 'UK'},
+                   *airport{code: b, city: c2, country: 'UK'},
+                   c1 = c2, a < b
+    "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(
+        rows["rows"],
+        json!([
+            ["BFS", "BHD"],
+            ["GLA", "PIK"],
+            ["LCY", "LGW"],
+            ["LCY", "LHR"],
+            ["LCY", "LTN"],
+            ["LCY", "STN"],
+            ["LGW", "LHR"],
+            ["LGW", "LTN"],
+            ["LGW", "STN"],
+            ["LHR", "LTN"],
+            ["LHR", "STN"],
+            ["LSI", "LWK"],
+            ["LTN", "STN"]
+        ])
+    );
+
+    let by_unification = TEST_DB
+        .run_default(
+            r#"
+        ?[a, b] := *airport{code: a, city: c1, country: 'UK'},
+                   *airport{code: b, city: c2, country: 'UK'},
+                   c1 = c2, a != b
+    "#,
+        )
+        .unwrap()
+        .into_json();
+    let by_shared_var = TEST_DB
+        .run_default(
+            r#"
+        ?[a, b] := *airport{code: a, city, country: 'UK'},
+                   *airport{code: b, city, country: 'UK'},
+                   a != b
+    "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(by_unification["rows"], by_shared_var["rows"]);
+    assert_eq!(by_unification["rows"].as_array().unwrap().len(), 26);
+    dbg!(same_city_pairs.elapsed());
+}
+
+#[test]
+fn runway_histogram() {
+    initialize(&TEST_DB);
+    let runway_histogram = Instant::now();
+
+    let rows = TEST_DB
+        .column_value_histogram("airport", "runways", 5)
+        .unwrap()
+        .into_json();
+    assert_eq!(rows["headers"], json!(["runways", "count"]));
+    assert_eq!(
+        rows["rows"],
+        json!([[1, 2429], [2, 775], [3, 227], [4, 53], [5, 14]])
+    );
+    assert!(TEST_DB
+        .column_value_histogram("airport", "no_such_column", 5)
+        .is_err());
+    dbg!(runway_histogram.elapsed());
+}
+
+#[test]
+fn get_value_by_key() {
+    initialize(&TEST_DB);
+    let get_value_by_key = Instant::now();
+
+    let city = TEST_DB
+        .get_value("airport", &[DataValue::from("AUS")], "city")
+        .unwrap();
+    assert_eq!(city, Some(DataValue::from("Austin")));
+    let code = TEST_DB
+        .get_value("airport", &[DataValue::from("AUS")], "code")
+        .unwrap();
+    assert_eq!(code, Some(DataValue::from("AUS")));
+    let missing = TEST_DB
+        .get_value("airport", &[DataValue::from("XXX")], "city")
+        .unwrap();
+    assert_eq!(missing, None);
+    assert!(TEST_DB
+        .get_value("airport", &[DataValue::from("AUS")], "no_such_column")
+        .is_err());
+    assert!(TEST_DB.get_value("airport", &[], "city").is_err());
+    dbg!(get_value_by_key.elapsed());
+}
+
+#[test]
+fn get_rows_by_keys() {
+    initialize(&TEST_DB);
+    let get_rows_by_keys = Instant::now();
+
+    let keys = ["LHR", "XXX", "AUS"]
+        .into_iter()
+        .map(|code| vec![DataValue::from(code)])
+        .collect::<Vec<_>>();
+    let rows = TEST_DB.get_rows("airport", &keys).unwrap();
+    assert_eq!(rows.len(), 3);
+    let city = |row: &Option<Vec<DataValue>>| row.as_ref().map(|row| row[8].clone());
+    assert_eq!(rows[0].as_ref().unwrap()[0], DataValue::from("LHR"));
+    assert_eq!(city(&rows[0]), Some(DataValue::from("London")));
+    assert_eq!(rows[1], None);
+    assert_eq!(city(&rows[2]), Some(DataValue::from("Austin")));
+    assert!(TEST_DB.get_rows("airport", &[vec![]]).is_err());
+    dbg!(get_rows_by_keys.elapsed());
+}
+
+#[test]
+fn count_query() {
+    initialize(&TEST_DB);
+    let count_query = Instant::now();
+
+    let n = TEST_DB
+        .count_query(
+            r#"
+        route_count[fr, count(fr)] := *route{fr};
+        ?[code, n] := route_count[code, n], n > 180;
+        :sort -n;
+    "#,
+            Default::default(),
+        )
+        .unwrap();
+    assert_eq!(n, 35);
+    let n = TEST_DB
+        .count_query("?[code] := *airport{code}", Default::default())
+        .unwrap();
+    assert_eq!(n, 3504);
+    let n = TEST_DB
+        .count_query(
+            "?[code] := *airport{code} :limit 10 :offset 3500",
+            Default::default(),
+        )
+        .unwrap();
+    assert_eq!(n, 4);
+    let n = TEST_DB
+        .count_query("?[count(code)] := *airport{code}", Default::default())
+        .unwrap();
+    assert_eq!(n, 1);
+    assert!(TEST_DB
+        .count_query(
+            "?[code] <- [['XXX']] :put airport {code}",
+            Default::default()
+        )
+        .is_err());
+    dbg!(count_query.elapsed());
+}
+
+#[test]
+fn aggr_value_caps() {
+    initialize(&TEST_DB);
+    let aggr_value_caps = Instant::now();
+
+    let rows = TEST_DB
+        .run_default(
+            r#"
+        ?[country, collect(code)] := *airport{code, country}, country == 'FR';
+        :max_group_values 100;
+        :max_aggr_values 100;
+    "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(rows["rows"][0][1].as_array().unwrap().len(), 59);
+
+    assert!(TEST_DB
+        .run_default(
+            r#"
+        ?[country, collect(code)] := *airport{code, country}, country == 'FR';
+        :max_group_values 50;
+    "#,
+        )
+        .is_err());
+    assert!(TEST_DB
+        .run_default(
+            r#"
+        ?[country, collect(code)] := *airport{code, country};
+        :max_group_values 1000;
+        :max_aggr_values 1000;
+    "#,
+        )
+        .is_err());
+    let rows = TEST_DB
+        .run_default(
+            r#"
+        ?[count(code)] := *airport{code};
+        :max_aggr_values 1;
+    "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(rows["rows"], json!([[3504]]));
+    dbg!(aggr_value_caps.elapsed());
+}
+
+#[test]
+fn runways_per_country() {
+    initialize(&TEST_DB);
+    let runways_per_country = Instant::now();
+
+    let rows = TEST_DB
+        .run_default(
+            r#"
+        ?[country, sum(runways)] := *airport{country, runways},
+                                    country in ['FR', 'DE', 'NZ']
+    "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(rows["rows"], json!([["DE", 55], ["FR", 88], ["NZ", 77]]));
+
+    let rows = TEST_DB
+        .run_default("?[sum(x)] := x in [1, 2.5, 3]")
+        .unwrap()
+        .into_json();
+    assert_eq!(rows["rows"], json!([[6.5]]));
+    assert!(TEST_DB
+        .run_default("?[sum(code)] := *airport{code}")
+        .is_err());
+    dbg!(runways_per_country.elapsed());
+}
+
+#[test]
+fn elevation_range_by_region() {
+    initialize(&TEST_DB);
+    let elevation_range_by_region = Instant::now();
+
+    let rows = TEST_DB
+        .run_default(
+            r#"
+        ?[region, min(elev), max(elev), avg(elev)] := *airport{region, elev},
+                                                      region in ['FR-J', 'AE-DU', 'CN-31']
+    "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(
+        rows["rows"],
+        json!([
+            ["AE-DU", 62.0, 114.0, 88.0],
+            ["CN-31", 10.0, 13.0, 11.5],
+            ["FR-J", 291.0, 392.0, 341.5]
+        ])
+    );
+
+    let rows = TEST_DB
+        .run_default("?[count(elev), avg(elev)] := *airport{region: 'XX', elev}")
+        .unwrap()
+        .into_json();
+    assert_eq!(rows["rows"], json!([[0, null]]));
+    dbg!(elevation_range_by_region.elapsed());
+}
+
 #[test]
 fn most_out_routes() {
     initialize(&TEST_DB);
@@ -500,7 +1029,328 @@ fn most_out_routes() {
         )
         .unwrap()
     );
-    dbg!(most_out_routes.elapsed());
+    dbg!(most_out_routes.elapsed());
+}
+
+#[test]
+fn most_out_routes_by_keyset_pages() {
+    initialize(&TEST_DB);
+    let most_out_routes_by_keyset_pages = Instant::now();
+
+    let all = TEST_DB
+        .run_default(
+            r#"
+        route_count[fr, count(fr)] := *route{fr};
+        ?[code, n] := route_count[code, n], n > 180;
+        :sort -n, code;
+    "#,
+        )
+        .unwrap()
+        .rows;
+
+    // each page seeks past the last `(n, code)` of the previous one
+    let mut paged = vec![];
+    let mut last = (DataValue::from(i64::MAX), DataValue::from(""));
+    loop {
+        let page = TEST_DB
+            .run_script(
+                r#"
+            route_count[fr, count(fr)] := *route{fr};
+            ?[code, n] := route_count[code, n], n > 180,
+                          n < $last_n || (n == $last_n && code > $last_code);
+            :sort -n, code;
+            :limit 10;
+        "#,
+                BTreeMap::from([
+                    ("last_n".to_string(), last.0.clone()),
+                    ("last_code".to_string(), last.1.clone()),
+                ]),
+                ScriptMutability::Immutable,
+            )
+            .unwrap()
+            .rows;
+        match page.last() {
+            None => break,
+            Some(row) => last = (row[1].clone(), row[0].clone()),
+        }
+        assert!(page.len() <= 10);
+        paged.extend(page);
+    }
+    assert_eq!(paged, all);
+    dbg!(most_out_routes_by_keyset_pages.elapsed());
+}
+
+#[test]
+fn most_out_routes_by_offset_pages() {
+    initialize(&TEST_DB);
+    let most_out_routes_by_offset_pages = Instant::now();
+
+    let script = r#"
+        route_count[fr, count(fr)] := *route{fr};
+        ?[code, n] := route_count[code, n], n > 180;
+        :sort -n, code;
+        :limit $page_size;
+        :offset $page_offset;
+    "#;
+    let page = |offset: i64| {
+        TEST_DB
+            .run_script(
+                script,
+                BTreeMap::from([
+                    ("page_size".to_string(), DataValue::from(10)),
+                    ("page_offset".to_string(), DataValue::from(offset)),
+                ]),
+                ScriptMutability::Immutable,
+            )
+            .unwrap()
+            .rows
+    };
+
+    let first = page(0);
+    assert_eq!(first.len(), 10);
+    assert_eq!(first[0][0], DataValue::from("FRA"));
+    let last = page(30);
+    assert_eq!(last.len(), 5);
+    assert_eq!(last[4][0], DataValue::from("SVO"));
+    assert!(page(40).is_empty());
+
+    let paged = (0..4).flat_map(|i| page(i * 10)).collect::<Vec<_>>();
+    assert_eq!(paged.len(), 35);
+    assert_eq!(paged[..10], first[..]);
+    assert_eq!(paged[30..], last[..]);
+
+    assert!(TEST_DB
+        .run_script(
+            script,
+            BTreeMap::from([
+                ("page_size".to_string(), DataValue::from(10)),
+                ("page_offset".to_string(), DataValue::from(-1)),
+            ]),
+            ScriptMutability::Immutable,
+        )
+        .is_err());
+    dbg!(most_out_routes_by_offset_pages.elapsed());
+}
+
+#[test]
+fn airports_by_bound_params() {
+    initialize(&TEST_DB);
+    let airports_by_bound_params = Instant::now();
+
+    let script = r#"
+        ?[code, runways] := *airport{code, country: $country, runways}, runways >= $min_runways
+        :order code
+    "#;
+    let run = |params: BTreeMap<String, DataValue>| {
+        TEST_DB.run_script(script, params, ScriptMutability::Immutable)
+    };
+
+    let res = run(BTreeMap::from([
+        ("country".to_string(), DataValue::from("DE")),
+        ("min_runways".to_string(), DataValue::from(3)),
+    ]))
+    .unwrap();
+    assert_eq!(
+        res.into_json()["rows"],
+        json!([["CGN", 3], ["FRA", 4], ["HAJ", 3]])
+    );
+
+    // a bound string is a value, never spliced into the script
+    let res = run(BTreeMap::from([
+        ("country".to_string(), DataValue::from("DE'} or true")),
+        ("min_runways".to_string(), DataValue::from(0)),
+    ]))
+    .unwrap();
+    assert!(res.rows.is_empty());
+
+    assert!(run(BTreeMap::from([(
+        "country".to_string(),
+        DataValue::from("DE")
+    )]))
+    .is_err());
+    dbg!(airports_by_bound_params.elapsed());
+}
+
+#[test]
+fn above_average_out_routes() {
+    initialize(&TEST_DB);
+    let above_average_out_routes = Instant::now();
+
+    let res = TEST_DB
+        .run_default(
+            r#"
+        route_count[fr, count(fr)] := *route{fr};
+        avg_count[mean(n)] := route_count[_, n];
+        ?[code, n, avg] := route_count[code, n], avg_count[avg], n > avg;
+        :order -n, code
+    "#,
+        )
+        .unwrap();
+    assert_eq!(res.rows.len(), 705);
+    let avg = res.rows[0][2].get_float().unwrap();
+    assert!(avg.abs_diff_eq(&14.571798561151079, 1e-8));
+    assert!(res.rows.iter().all(|row| row[1].get_float().unwrap() > avg));
+    assert_eq!(
+        res.rows[..3]
+            .iter()
+            .map(|row| row[0].get_str().unwrap())
+            .collect::<Vec<_>>(),
+        ["FRA", "IST", "CDG"]
+    );
+    dbg!(above_average_out_routes.elapsed());
+}
+
+#[test]
+fn join_two_count_rules() {
+    initialize(&TEST_DB);
+    let join_two_count_rules = Instant::now();
+
+    let rows = TEST_DB
+        .run_default(
+            r#"
+        out_count[code, count(to)] := *route{fr: code, to};
+        in_count[code, count(fr)] := *route{fr, to: code};
+        ?[code, diff] := out_count[code, o], in_count[code, i], diff = o - i, diff != 0;
+        :order diff, code
+    "#,
+        )
+        .unwrap()
+        .rows;
+    assert_eq!(rows.len(), 334);
+    assert_eq!(rows[0], vec![DataValue::from("PKX"), DataValue::from(-11)]);
+    assert_eq!(
+        rows[rows.len() - 1],
+        vec![DataValue::from("NAU"), DataValue::from(6)]
+    );
+    dbg!(join_two_count_rules.elapsed());
+}
+
+#[test]
+fn distinct_cities() {
+    initialize(&TEST_DB);
+    let distinct_cities = Instant::now();
+
+    let rows = TEST_DB
+        .run_default("?[city] := *airport{city}; :sort -city")
+        .unwrap()
+        .rows;
+    assert_eq!(rows.len(), 3360);
+    assert!(rows.windows(2).all(|w| w[0] > w[1]));
+    dbg!(distinct_cities.elapsed());
+}
+
+#[test]
+fn cities_with_more_than_three_airports() {
+    initialize(&TEST_DB);
+    let cities_with_more_than_three_airports = Instant::now();
+
+    let rows = TEST_DB
+        .run_default(
+            r#"
+        city_codes[city, collect(code)] := *airport{code, city};
+        ?[city, codes] := city_codes[city, codes], length(codes) > 3;
+    "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(
+        rows["rows"],
+        json!([
+            ["London", ["LCY", "LGW", "LHR", "LTN", "STN", "YXU"]],
+            ["Melbourne", ["AVV", "MEB", "MEL", "MLB"]],
+            ["San Jose", ["SJC", "SJI", "SJO", "SYQ"]],
+            ["Santa Rosa", ["ETR", "RSA", "SRA", "STS"]]
+        ])
+    );
+    dbg!(cities_with_more_than_three_airports.elapsed());
+}
+
+#[test]
+fn order_by_aggregate() {
+    initialize(&TEST_DB);
+    let order_by_aggregate = Instant::now();
+
+    let by_aggregate = TEST_DB
+        .run_default(
+            r#"
+        ?[fr, count(to)] := *route{fr, to}
+        :order -count(to), fr
+        :limit 5
+    "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(
+        by_aggregate["rows"],
+        json!([
+            ["FRA", 310],
+            ["IST", 309],
+            ["CDG", 293],
+            ["AMS", 283],
+            ["MUC", 270]
+        ])
+    );
+
+    let by_alias = TEST_DB
+        .run_default(
+            r#"
+        route_count[fr, count(to)] := *route{fr, to}
+        ?[code, n] := route_count[code, n]
+        :order -n, code
+        :limit 5
+    "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(by_alias["rows"], by_aggregate["rows"]);
+
+    assert!(TEST_DB
+        .run_default("?[fr, count(to)] := *route{fr, to} :order -to")
+        .is_err());
+    dbg!(order_by_aggregate.elapsed());
+}
+
+#[test]
+fn order_ties_broken_by_second_key() {
+    initialize(&TEST_DB);
+    let order_ties_broken_by_second_key = Instant::now();
+
+    let ordered = |order: &str| {
+        TEST_DB
+            .run_default(&format!(
+                r#"
+        route_count[fr, count(to)] := *route{{fr, to}}
+        ?[code, n] := route_count[code, n]
+        :order {order}
+        :offset 7
+        :limit 5
+    "#
+            ))
+            .unwrap()
+            .into_json()["rows"]
+            .clone()
+    };
+    assert_eq!(
+        ordered("-n, code"),
+        json!([
+            ["DXB", 248],
+            ["PEK", 248],
+            ["ATL", 242],
+            ["DME", 232],
+            ["LGW", 232]
+        ])
+    );
+    assert_eq!(
+        ordered("-n, -code"),
+        json!([
+            ["PEK", 248],
+            ["DXB", 248],
+            ["ATL", 242],
+            ["LGW", 232],
+            ["DME", 232]
+        ])
+    );
+    dbg!(order_ties_broken_by_second_key.elapsed());
 }
 
 #[test]
@@ -618,6 +1468,41 @@ fn single_runway_with_most_routes() {
     dbg!(single_runway_with_most_routes.elapsed());
 }
 
+#[test]
+fn most_routes_by_union_rule() {
+    initialize(&TEST_DB);
+    let most_routes_by_union_rule = Instant::now();
+
+    let rows = TEST_DB
+        .run_default(
+            r#"
+        route_endpoint[fr, to, a] := *route{fr, to}, a = fr
+        route_endpoint[fr, to, a] := *route{fr, to}, a = to
+        route_count[a, count(fr)] := route_endpoint[fr, _, a]
+        ?[code, n] := route_count[code, n], n > 400
+        :sort -n;
+    "#,
+        )
+        .unwrap()
+        .into_json();
+
+    let expected = TEST_DB
+        .run_default(
+            r#"
+        route_count[a, count(a)] := *route{fr: a}
+        route_count[a, count(a)] := *route{to: a}
+        ?[code, n] := route_count[code, n], n > 400
+        :sort -n;
+    "#,
+        )
+        .unwrap()
+        .into_json();
+
+    assert_eq!(rows["rows"], expected["rows"]);
+    assert_eq!(rows["rows"][0], json!(["FRA", 620]));
+    dbg!(most_routes_by_union_rule.elapsed());
+}
+
 #[test]
 fn most_routes_in_canada() {
     initialize(&TEST_DB);
@@ -675,6 +1560,85 @@ fn uk_count() {
     dbg!(uk_count.elapsed());
 }
 
+#[test]
+fn uk_regions_by_regex() {
+    initialize(&TEST_DB);
+    let uk_regions_by_regex = Instant::now();
+
+    let rows = TEST_DB
+        .run_default(
+            r#"
+       ?[region, count(region)] := *airport{region}, regex_matches(region, '^GB-')
+    "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(
+        rows["rows"],
+        json!([["GB-ENG", 27], ["GB-NIR", 3], ["GB-SCT", 25], ["GB-WLS", 3]])
+    );
+
+    // a constant pattern is compiled once, when the query is compiled
+    assert!(TEST_DB
+        .run_default("?[region] := *airport{region}, regex_matches(region, '^GB-(')")
+        .is_err());
+    dbg!(uk_regions_by_regex.elapsed());
+}
+
+#[test]
+fn airports_by_country_outer_aggregation() {
+    initialize(&TEST_DB);
+    let airports_by_country_outer_aggregation = Instant::now();
+
+    let two_rules = TEST_DB
+        .run_default(
+            r#"
+        airports_by_country[country, count(code)] := *airport{code, country}
+        ?[country, count] := airports_by_country[country, count];
+        ?[country, count] := *country{code: country}, not airports_by_country[country, _], count = 0
+    "#,
+        )
+        .unwrap()
+        .into_json();
+
+    let one_rule = TEST_DB
+        .run_default(
+            r#"
+        ?[country, count_non_null(code)] := *country{code: country}, optional *airport{code, country}
+    "#,
+        )
+        .unwrap()
+        .into_json();
+
+    assert_eq!(one_rule["rows"], two_rules["rows"]);
+    assert_eq!(one_rule["rows"][0], json!(["AD", 0]));
+    dbg!(airports_by_country_outer_aggregation.elapsed());
+}
+
+#[test]
+fn unordered_results_are_stable() {
+    initialize(&TEST_DB);
+    let unordered_results_are_stable = Instant::now();
+
+    let query = r#"
+        airports_by_country[country, count(code)] := *airport{code, country}
+        ?[country, count] := airports_by_country[country, count]
+    "#;
+    let first = TEST_DB.run_default(query).unwrap().into_json();
+    let second = TEST_DB.run_default(query).unwrap().into_json();
+    assert_eq!(first["rows"].to_string(), second["rows"].to_string());
+
+    // without `:order`, rows come out sorted by the full output tuple
+    let countries = first["rows"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|row| row[0].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert!(countries.windows(2).all(|w| w[0] < w[1]));
+    dbg!(unordered_results_are_stable.elapsed());
+}
+
 #[test]
 fn airports_by_country() {
     initialize(&TEST_DB);
@@ -754,6 +1718,51 @@ fn n_airports_by_continent() {
     dbg!(n_airports_by_continent.elapsed());
 }
 
+#[test]
+fn airports_by_continent_and_country_ordered() {
+    initialize(&TEST_DB);
+    let airports_by_continent_and_country_ordered = Instant::now();
+
+    let rows = TEST_DB
+        .run_default(
+            r#"
+        by_country[cont, country, count(code)] := *airport{code, country}, *contain[cont, code]
+        ?[cont, country, n] := by_country[cont, country, n], n >= 50
+        :order cont, -n
+    "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(
+        rows["rows"],
+        serde_json::Value::from_str(
+            r#"[
+            ["AS","CN",217],["AS","IN",77],["AS","ID",70],["AS","RU",67],["AS","JP",65],
+            ["EU","RU",62],["EU","FR",59],["EU","UK",58],["NA","US",585],["NA","CA",205],
+            ["NA","MX",60],["OC","AU",131],["SA","BR",117],["SA","CO",51]
+            ]"#
+        )
+        .unwrap()
+    );
+
+    // sorting on the aggregate of the entry rule itself
+    let rows = TEST_DB
+        .run_default(
+            r#"
+        ?[cont, country, count(code)] := *airport{code, country}, *contain[cont, code]
+        :order cont, -code
+        :limit 3
+    "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(
+        rows["rows"],
+        json!([["AF", "DZ", 30], ["AF", "ZA", 20], ["AF", "NG", 19]])
+    );
+    dbg!(airports_by_continent_and_country_ordered.elapsed());
+}
+
 #[test]
 fn routes_per_airport() {
     initialize(&TEST_DB);
@@ -822,6 +1831,36 @@ fn out_from_aus() {
     dbg!(out_from_aus.elapsed());
 }
 
+#[test]
+fn out_from_aus_shares_bindings() {
+    initialize(&TEST_DB);
+    let out_from_aus_shares_bindings = Instant::now();
+
+    let query = r#"
+        two_hops[count(a)] := aus = 'AUS', *airport{code: aus}, *route{fr: aus, to: a},
+                              *airport{code: a}, *route{fr: a}
+        ?[total] := two_hops[total]
+    "#;
+
+    let plan = TEST_DB
+        .run_default(&format!("::explain {{ {query} }}"))
+        .unwrap()
+        .into_json();
+    for row in plan["rows"].as_array().unwrap() {
+        let op = row[4].as_str().unwrap();
+        if op.ends_with("_join") {
+            assert!(
+                !row[6].as_object().unwrap().is_empty(),
+                "cartesian join in plan: {row}"
+            );
+        }
+    }
+
+    let rows = TEST_DB.run_default(query).unwrap().into_json();
+    assert_eq!(rows["rows"], json!([[8354]]));
+    dbg!(out_from_aus_shares_bindings.elapsed());
+}
+
 #[test]
 fn const_return() {
     initialize(&TEST_DB);
@@ -1019,7 +2058,7 @@ fn len_of_names_count() {
 
     assert_eq!(
         rows["rows"],
-        serde_json::Value::from_str(r#"[[891.0]]"#).unwrap()
+        serde_json::Value::from_str(r#"[[891]]"#).unwrap()
     );
     dbg!(len_of_names_count.elapsed());
 }
@@ -1836,6 +2875,41 @@ fn aus_to_edi() {
     dbg!(aus_to_edi.elapsed());
 }
 
+#[test]
+fn airports_reachable_by_closure() {
+    initialize(&TEST_DB);
+    let airports_reachable_by_closure = Instant::now();
+
+    let rows = TEST_DB
+        .run_default(
+            r#"
+        reach[to] := *route{fr: 'AUS', to}
+        reach[to] := reach[a], *route{fr: a, to}
+        ?[count(code)] := reach[code]
+        "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(rows["rows"], json!([[3462]]));
+
+    // a small island network whose routes form cycles
+    let rows = TEST_DB
+        .run_default(
+            r#"
+        reach[to] := *route{fr: 'CAT', to}
+        reach[to] := reach[a], *route{fr: a, to}
+        ?[code] := reach[code]
+        "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(
+        rows["rows"],
+        json!([["BGC"], ["CAT"], ["PRM"], ["VRL"], ["VSE"]])
+    );
+    dbg!(airports_reachable_by_closure.elapsed());
+}
+
 #[test]
 fn reachable_from_lhr() {
     initialize(&TEST_DB);
@@ -1980,3 +3054,247 @@ fn skip_limit() {
 
     assert_eq!(rows["rows"], json!([[3], [4], [5], [6], [7], [8]]));
 }
+
+#[test]
+fn longer_routes_than_aus_lhr() {
+    initialize(&TEST_DB);
+    let longer_routes_than_aus_lhr = Instant::now();
+
+    let rows = TEST_DB
+        .run_default(
+            r#"
+        ?[to, d2] := *route{fr: 'AUS', to: 'LHR', dist: d1}, *route{fr: 'AUS', to, dist: d2}, d2 > d1
+        :order -d2
+    "#,
+        )
+        .unwrap()
+        .into_json();
+
+    let expected = TEST_DB
+        .run_default(
+            r#"
+        ?[to, dist] := *route{fr: 'AUS', to, dist}, dist > 4901
+        :order -dist
+    "#,
+        )
+        .unwrap()
+        .into_json();
+
+    assert_eq!(rows["rows"], expected["rows"]);
+    dbg!(longer_routes_than_aus_lhr.elapsed());
+}
+
+#[test]
+fn or_of_conjunctions() {
+    initialize(&TEST_DB);
+    let or_of_conjunctions = Instant::now();
+
+    let by_or = TEST_DB
+        .run_default(
+            r#"
+            ?[code, country] :=
+                (*route{fr: 'AUS', to: code}, *airport{code, country}, country == 'MX') or
+                (*route{fr: 'LHR', to: code}, *airport{code, country}, country == 'IE')
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    let by_rules = TEST_DB
+        .run_default(
+            r#"
+            ?[code, country] :=
+                *route{fr: 'AUS', to: code}, *airport{code, country}, country == 'MX'
+            ?[code, country] :=
+                *route{fr: 'LHR', to: code}, *airport{code, country}, country == 'IE'
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(by_or["rows"], by_rules["rows"]);
+    let rows = by_or["rows"].as_array().unwrap();
+    assert!(rows.contains(&json!(["CUN", "MX"])));
+    assert!(rows.contains(&json!(["DUB", "IE"])));
+    dbg!(or_of_conjunctions.elapsed());
+}
+
+#[test]
+fn flatten_collected_cities() {
+    initialize(&TEST_DB);
+    let flatten_collected_cities = Instant::now();
+
+    let res = TEST_DB
+        .run_default(
+            r#"
+            cities[country, collect(city)] := *airport{country, city}, country in ['IE', 'IS']
+            ?[flatten(cities)] := cities[_, cities]
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    let flat = res["rows"][0][0].as_array().unwrap().clone();
+
+    let res = TEST_DB
+        .run_default(
+            r#"
+            ?[country, code, city] := *airport{code, country, city}, country in ['IE', 'IS']
+            :order country, code
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    let expected = res["rows"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|row| row[2].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(flat, expected);
+    dbg!(flatten_collected_cities.elapsed());
+}
+
+#[test]
+fn concat_coerces_operands() {
+    initialize(&TEST_DB);
+    let concat_coerces_operands = Instant::now();
+
+    let res = TEST_DB
+        .run_default(
+            "?[code, label] := code in ['AUS', 'DFW'], *airport{code, runways}, label = 'region-' ++ runways",
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(
+        res["rows"],
+        json!([["AUS", "region-2"], ["DFW", "region-7"]])
+    );
+    dbg!(concat_coerces_operands.elapsed());
+}
+
+#[test]
+fn reused_route_count_rule() {
+    initialize(&TEST_DB);
+    let reused_route_count_rule = Instant::now();
+
+    let inline = TEST_DB
+        .run_default("?[code, count(to)] := *route{fr: code, to}")
+        .unwrap()
+        .into_json();
+    let by_rule = TEST_DB
+        .run_default(
+            r#"
+            route_count[a, count(r)] := *route{fr: a, to: r}
+            ?[code, n] := route_count[code, n]
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(inline["rows"], by_rule["rows"]);
+
+    let out_counts: BTreeMap<_, _> = inline["rows"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|row| (row[0].as_str().unwrap(), row[1].as_i64().unwrap()))
+        .collect();
+    let hub_pairs = TEST_DB
+        .run_default(
+            r#"
+            route_count[a, count(r)] := *route{fr: a, to: r}
+            ?[a, b] := *route{fr: a, to: b},
+                       route_count[a, na], na > 250,
+                       route_count[b, nb], nb > 250
+            "#,
+        )
+        .unwrap()
+        .into_json();
+    let hub_pairs = hub_pairs["rows"].as_array().unwrap();
+    assert!(!hub_pairs.is_empty());
+    for pair in hub_pairs {
+        assert!(out_counts[pair[0].as_str().unwrap()] > 250);
+        assert!(out_counts[pair[1].as_str().unwrap()] > 250);
+    }
+    dbg!(reused_route_count_rule.elapsed());
+}
+
+#[test]
+fn explain_analyze_join() {
+    initialize(&TEST_DB);
+    let explain_analyze_join = Instant::now();
+
+    let query = "?[code, dist] := *airport{code, country: 'IS'}, *route{fr: code, to: 'LHR', dist}";
+    let res = TEST_DB.run_default(query).unwrap();
+    let analyzed = TEST_DB
+        .run_script_explain_analyze(query, Default::default())
+        .unwrap()
+        .into_json();
+    let headers = analyzed["headers"].as_array().unwrap();
+    let col = |name: &str| headers.iter().position(|h| h == name).unwrap();
+    let (op, rule, actual_rows, ms) = (col("op"), col("rule"), col("actual_rows"), col("ms"));
+    let rows = analyzed["rows"].as_array().unwrap();
+    let root = rows
+        .iter()
+        .find(|row| row[op] == "out" && row[rule] == "?")
+        .unwrap();
+    assert_eq!(root[actual_rows], json!(res.rows.len()));
+    assert!(root[ms].as_f64().unwrap() >= 0.);
+    assert!(rows
+        .iter()
+        .any(|row| row[op] == "load_stored" && row[actual_rows].as_u64().unwrap() > 1000));
+
+    assert!(TEST_DB
+        .run_script_explain_analyze(
+            "?[code] <- [['XXX']] :put airport {code}",
+            Default::default()
+        )
+        .is_err());
+    dbg!(explain_analyze_join.elapsed());
+}
+
+#[test]
+fn trace_query_join() {
+    initialize(&TEST_DB);
+    let trace_query_join = Instant::now();
+
+    let query = "?[code, dist] := *airport{code, country: 'IS'}, *route{fr: code, to: 'LHR', dist}";
+    let res = TEST_DB.run_default(query).unwrap();
+    let (plan, traces) = TEST_DB.trace_query(query, Default::default(), 3).unwrap();
+    assert!(plan.headers.contains(&"actual_rows".to_string()));
+    assert!(traces
+        .iter()
+        .all(|t| !t.samples.is_empty() && t.samples.len() <= 3));
+
+    let scan = traces.iter().find(|t| t.op == "load_stored").unwrap();
+    assert_eq!(scan.samples.len(), 3);
+    let join = traces.iter().find(|t| t.op.ends_with("_join")).unwrap();
+    assert!(join.atom_idx < scan.atom_idx);
+    let codes: Vec<_> = res.rows.iter().map(|row| row[0].clone()).collect();
+    for tuple in &join.samples {
+        assert!(tuple.iter().any(|v| codes.contains(v)));
+    }
+
+    let (_, traces) = TEST_DB.trace_query(query, Default::default(), 0).unwrap();
+    assert!(traces.is_empty());
+    dbg!(trace_query_join.elapsed());
+}
+
+#[test]
+fn airports_nested_by_country() {
+    initialize(&TEST_DB);
+    let airports_nested_by_country = Instant::now();
+
+    let res = TEST_DB
+        .run_default(
+            "?[country, code, city] := country in ['BB', 'JM', 'TT'], *airport{code, city, country}",
+        )
+        .unwrap();
+    assert_eq!(
+        res.clone().nest_by("country").unwrap(),
+        json!({
+            "BB": [{"code": "BGI", "city": "Bridgetown"}],
+            "JM": [{"code": "KIN", "city": "Kingston"}, {"code": "MBJ", "city": "Montego Bay"}],
+            "TT": [{"code": "POS", "city": "Port of Spain"}, {"code": "TAB", "city": "Scarborough"}],
+        })
+    );
+    assert!(res.nest_by("region").is_err());
+    dbg!(airports_nested_by_country.elapsed());
+}
@@ -110,6 +110,16 @@ struct RocksDbBridge {
         write_status(s, status);
     }
 
+    void flush(RocksDbStatus &status) const {
+        FlushOptions options;
+        options.wait = true;
+        auto s = db->Flush(options);
+        if (s.ok()) {
+            s = db->FlushWAL(true);
+        }
+        write_status(s, status);
+    }
+
     DB *get_base_db() const {
         return db->GetBaseDB();
     }
@@ -7,7 +7,11 @@
  */
 
 use cxx::*;
+use std::env;
 use std::path::Path;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::bridge::ffi::*;
 use crate::bridge::tx::TxBuilder;
@@ -63,6 +67,28 @@ impl DbBuilder {
         self.opts.db_path = path2buf(path);
         self
     }
+    /// Use a new, uniquely named directory under the system temporary directory.
+    /// The database is destroyed when it is dropped.
+    pub fn temp(mut self) -> Self {
+        static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let path = env::temp_dir().join(format!(
+            "cozorocks-{}-{}-{}",
+            process::id(),
+            nanos,
+            TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        self.opts.create_if_missing = true;
+        self.opts.destroy_on_exit = true;
+        self.path(path)
+    }
+    pub fn destroy_on_exit(mut self, val: bool) -> Self {
+        self.opts.destroy_on_exit = val;
+        self
+    }
     pub fn options_path(mut self, path: impl AsRef<Path>) -> Self {
         self.opts.options_path = path2buf(path);
         self
@@ -177,6 +203,16 @@ impl RocksDb {
             Err(status)
         }
     }
+    #[inline]
+    pub fn flush(&self) -> Result<(), RocksDbStatus> {
+        let mut status = RocksDbStatus::default();
+        self.inner.flush(&mut status);
+        if status.is_ok() {
+            Ok(())
+        } else {
+            Err(status)
+        }
+    }
     pub fn get_sst_writer(&self, path: &str) -> Result<SstWriter, RocksDbStatus> {
         let mut status = RocksDbStatus::default();
         let ret = self.inner.get_sst_writer(path, &mut status);
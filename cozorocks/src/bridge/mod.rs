@@ -133,6 +133,7 @@ pub(crate) mod ffi {
             upper: &[u8],
             status: &mut RocksDbStatus,
         );
+        fn flush(self: &RocksDbBridge, status: &mut RocksDbStatus);
         fn get_sst_writer(
             self: &RocksDbBridge,
             path: &str,